@@ -1,9 +1,100 @@
-use std::fs;
+mod report;
+
+use std::{env, fs};
+
+#[derive(Clone, Copy, Debug)]
+struct SafetyPolicy {
+    min_step: i32,
+    max_step: i32,
+    // zero steps don't count towards the min, and don't set a direction
+    allow_zero_step: bool,
+    max_removals: usize,
+}
+
+impl SafetyPolicy {
+    const PART1: SafetyPolicy = SafetyPolicy {
+        min_step: 1,
+        max_step: 3,
+        allow_zero_step: false,
+        max_removals: 0,
+    };
+
+    const PART2: SafetyPolicy = SafetyPolicy {
+        max_removals: 1,
+        ..SafetyPolicy::PART1
+    };
+
+    fn step_ok(&self, change: i32, increasing: bool) -> bool {
+        if change == 0 {
+            return self.allow_zero_step;
+        }
+        (change > 0) == increasing && (self.min_step..=self.max_step).contains(&change.abs())
+    }
+
+    fn is_safe(&self, report: &[i32]) -> bool {
+        self.removals_needed(report).is_some()
+    }
+
+    // smallest set of indices to drop to make the report safe, if we're allowed that many.
+    fn removals_needed(&self, report: &[i32]) -> Option<Vec<usize>> {
+        [true, false]
+            .iter()
+            .map(|&increasing| self.longest_safe_subsequence(report, increasing))
+            .max_by_key(|kept| kept.len())
+            .map(|kept| {
+                (0..report.len())
+                    .filter(|i| !kept.contains(i))
+                    .collect::<Vec<usize>>()
+            })
+            .filter(|removed| removed.len() <= self.max_removals)
+    }
+
+    // longest[i] is the longest safe run of kept levels that ends by keeping level i
+    fn longest_safe_subsequence(&self, report: &[i32], increasing: bool) -> Vec<usize> {
+        let mut longest: Vec<usize> = vec![1; report.len()];
+        let mut prev: Vec<Option<usize>> = vec![None; report.len()];
+        for i in 0..report.len() {
+            for j in 0..i {
+                if longest[j] + 1 > longest[i] && self.step_ok(report[i] - report[j], increasing) {
+                    longest[i] = longest[j] + 1;
+                    prev[i] = Some(j);
+                }
+            }
+        }
+
+        let mut kept = Vec::new();
+        let mut cur = (0..report.len()).max_by_key(|&i| longest[i]);
+        while let Some(i) = cur {
+            kept.push(i);
+            cur = prev[i];
+        }
+        kept.reverse();
+        kept
+    }
+}
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("File read error");
     let reports: Vec<Vec<i32>> = input.lines().map(parse_line).collect();
-    
+
+    // cargo run -- report [--part1] [--json]
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "report") {
+        let policy = if args.iter().any(|a| a == "--part1") {
+            SafetyPolicy::PART1
+        } else {
+            SafetyPolicy::PART2
+        };
+        let diagnoses: Vec<report::Diagnosis> =
+            reports.iter().map(|r| policy.diagnose(r)).collect();
+        if args.iter().any(|a| a == "--json") {
+            print!("{}", report::format_json(&diagnoses));
+        } else {
+            print!("{}", report::format_text(&diagnoses));
+        }
+        return;
+    }
+
     let part1_answer = part1(&reports);
     println!("part 1: {}", part1_answer);
 
@@ -12,48 +103,86 @@ fn main() {
 }
 
 fn parse_line(line: &str) -> Vec<i32> {
-    line.split_whitespace().map(|n| n.parse().unwrap()).collect()
+    line.split_whitespace()
+        .map(|n| n.parse().unwrap())
+        .collect()
 }
 
-fn part1(reports: &Vec<Vec<i32>>) -> usize {
-    let is_safe_list: Vec<bool> = reports.iter().map(is_safe).collect();
-    // println!("{:#?}", is_safe_list);
-    is_safe_list.iter().filter(|&&r| r).count()
+fn part1(reports: &[Vec<i32>]) -> usize {
+    count_safe(reports, &SafetyPolicy::PART1)
 }
 
-fn part2(reports: &Vec<Vec<i32>>) -> usize {
-    let is_safe_list: Vec<bool> = reports.iter().map(is_safe2).collect();
-    // println!("{:#?}", is_safe_list);
-    is_safe_list.iter().filter(|&&r| r).count()
+fn part2(reports: &[Vec<i32>]) -> usize {
+    count_safe(reports, &SafetyPolicy::PART2)
 }
 
-fn is_safe(report: &Vec<i32>) -> bool {
-    let mut last_change: Option<i32> = None;
-    for i in 1..report.len() {
-        let change = report[i] - report[i-1];
-        if change == 0 || change.abs() > 3 {
-            return false;
-        }
-        if let Some(lc) = last_change {
-            if sign(change) != sign(lc) {
-                return false;
-            }
-        }
-        last_change = Some(change);
-    }
-    true
+fn count_safe(reports: &[Vec<i32>], policy: &SafetyPolicy) -> usize {
+    reports.iter().filter(|r| policy.is_safe(r)).count()
 }
 
-fn is_safe2(report: &Vec<i32>) -> bool {
-    (0..report.len()).any(|i| is_safe(&copy_and_remove_index(report, i)))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn copy_and_remove_index(report: &Vec<i32>, index: usize) -> Vec<i32> {
-    let mut new_vec = report.clone();
-    new_vec.remove(index);
-    new_vec
-}
+    fn get_toy_reports() -> Vec<Vec<i32>> {
+        let input = fs::read_to_string("toy_input.txt").unwrap();
+        input.lines().map(parse_line).collect()
+    }
 
-fn sign(n: i32) -> bool {
-    n > 0
+    #[test]
+    fn part1_toy_answer() {
+        assert_eq!(part1(&get_toy_reports()), 2);
+    }
+
+    #[test]
+    fn part2_toy_answer() {
+        assert_eq!(part2(&get_toy_reports()), 5);
+    }
+
+    #[test]
+    fn policy_with_two_removals() {
+        let policy = SafetyPolicy {
+            max_removals: 2,
+            ..SafetyPolicy::PART1
+        };
+        // 9 and 0 both have to go
+        let report = vec![1, 9, 2, 3, 0, 4];
+        assert!(!SafetyPolicy::PART2.is_safe(&report));
+        assert_eq!(policy.removals_needed(&report), Some(vec![1, 4]));
+    }
+
+    #[test]
+    fn policy_allowing_zero_steps() {
+        let policy = SafetyPolicy {
+            allow_zero_step: true,
+            ..SafetyPolicy::PART1
+        };
+        assert!(!SafetyPolicy::PART1.is_safe(&[1, 2, 2, 4]));
+        assert!(policy.is_safe(&[1, 2, 2, 4]));
+        assert!(!policy.is_safe(&[1, 2, 2, 1]));
+    }
+
+    #[test]
+    fn diagnoses_toy_reports() {
+        let diagnoses: Vec<report::Diagnosis> = get_toy_reports()
+            .iter()
+            .map(|r| SafetyPolicy::PART2.diagnose(r))
+            .collect();
+        assert_eq!(diagnoses[0].first_violation, None);
+        assert_eq!(
+            diagnoses[1].first_violation,
+            Some((2, report::Violation::StepTooLarge))
+        );
+        assert_eq!(diagnoses[1].removals, None);
+        assert_eq!(
+            diagnoses[3].first_violation,
+            Some((2, report::Violation::DirectionFlip))
+        );
+        assert_eq!(diagnoses[3].removals, Some(vec![1]));
+        assert_eq!(
+            diagnoses[4].first_violation,
+            Some((3, report::Violation::ZeroStep))
+        );
+        assert_eq!(diagnoses[4].removals, Some(vec![3]));
+    }
 }
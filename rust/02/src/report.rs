@@ -0,0 +1,125 @@
+use crate::SafetyPolicy;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+    ZeroStep,
+    StepTooSmall,
+    StepTooLarge,
+    DirectionFlip,
+}
+
+impl Violation {
+    fn describe(&self) -> &'static str {
+        match self {
+            Violation::ZeroStep => "zero step",
+            Violation::StepTooSmall => "step too small",
+            Violation::StepTooLarge => "step too large",
+            Violation::DirectionFlip => "direction flip",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnosis {
+    pub levels: Vec<i32>,
+    // index of the level whose step from the previous level broke a rule
+    pub first_violation: Option<(usize, Violation)>,
+    // None if it can't be made safe within the policy's removals
+    pub removals: Option<Vec<usize>>,
+}
+
+impl Diagnosis {
+    pub fn is_safe(&self) -> bool {
+        self.removals.is_some()
+    }
+}
+
+impl SafetyPolicy {
+    pub fn first_violation(&self, report: &[i32]) -> Option<(usize, Violation)> {
+        let mut increasing: Option<bool> = None;
+        for i in 1..report.len() {
+            let change = report[i] - report[i - 1];
+            if change == 0 {
+                if !self.allow_zero_step {
+                    return Some((i, Violation::ZeroStep));
+                }
+                continue;
+            }
+            if change.abs() < self.min_step {
+                return Some((i, Violation::StepTooSmall));
+            }
+            if change.abs() > self.max_step {
+                return Some((i, Violation::StepTooLarge));
+            }
+            match increasing {
+                Some(inc) if inc != (change > 0) => return Some((i, Violation::DirectionFlip)),
+                _ => increasing = Some(change > 0),
+            }
+        }
+        None
+    }
+
+    pub fn diagnose(&self, report: &[i32]) -> Diagnosis {
+        Diagnosis {
+            levels: report.to_vec(),
+            first_violation: self.first_violation(report),
+            removals: self.removals_needed(report),
+        }
+    }
+}
+
+pub fn format_text(diagnoses: &[Diagnosis]) -> String {
+    diagnoses
+        .iter()
+        .enumerate()
+        .map(|(line, d)| {
+            let levels: Vec<String> = d.levels.iter().map(|l| l.to_string()).collect();
+            let verdict = match (&d.first_violation, &d.removals) {
+                (None, _) => "safe".to_string(),
+                (Some((i, v)), Some(removed)) => format!(
+                    "safe after removing index {} ({} at index {})",
+                    join(removed),
+                    v.describe(),
+                    i
+                ),
+                (Some((i, v)), None) => format!("unsafe: {} at index {}", v.describe(), i),
+            };
+            format!("line {}: {} -> {}\n", line + 1, levels.join(" "), verdict)
+        })
+        .collect()
+}
+
+pub fn format_json(diagnoses: &[Diagnosis]) -> String {
+    let entries: Vec<String> = diagnoses
+        .iter()
+        .enumerate()
+        .map(|(line, d)| {
+            let (index, rule) = match d.first_violation {
+                Some((i, v)) => (i.to_string(), format!("\"{}\"", v.describe())),
+                None => ("null".to_string(), "null".to_string()),
+            };
+            let removals = match &d.removals {
+                Some(removed) => format!("[{}]", join(removed)),
+                None => "null".to_string(),
+            };
+            format!(
+                "  {{\"line\": {}, \"levels\": [{}], \"safe\": {}, \"first_violation_index\": {}, \"rule\": {}, \"removed_indices\": {}}}",
+                line + 1,
+                join(&d.levels),
+                d.is_safe(),
+                index,
+                rule,
+                removals
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
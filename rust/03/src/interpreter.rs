@@ -0,0 +1,160 @@
+use crate::tokenizer::{Tokens, MAX_ARITY};
+use std::io::{self, Read};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Effect {
+    // add the result to the total, but only while enabled. None if it doesn't fit in an i64.
+    Accumulate(fn(&[i64]) -> Option<i64>),
+    Enable,
+    Disable,
+    Toggle,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Instruction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub min_digits: usize,
+    pub max_digits: usize,
+    pub effect: Effect,
+}
+
+// instructions are identified by name, the effect is just how they run
+impl PartialEq for Instruction {
+    fn eq(&self, other: &Instruction) -> bool {
+        self.name == other.name
+    }
+}
+
+pub const MUL: Instruction = Instruction {
+    name: "mul",
+    arity: 2,
+    min_digits: 1,
+    max_digits: 3,
+    effect: Effect::Accumulate(|ops| ops.iter().try_fold(1i64, |acc, &op| acc.checked_mul(op))),
+};
+
+pub const ADD: Instruction = Instruction {
+    name: "add",
+    effect: Effect::Accumulate(|ops| ops.iter().try_fold(0i64, |acc, &op| acc.checked_add(op))),
+    ..MUL
+};
+
+pub const SUB: Instruction = Instruction {
+    name: "sub",
+    effect: Effect::Accumulate(|ops| ops[0].checked_sub(ops[1])),
+    ..MUL
+};
+
+pub const DO: Instruction = Instruction {
+    name: "do",
+    arity: 0,
+    min_digits: 0,
+    max_digits: 0,
+    effect: Effect::Enable,
+};
+
+pub const DONT: Instruction = Instruction {
+    name: "don't",
+    effect: Effect::Disable,
+    ..DO
+};
+
+pub const TOGGLE: Instruction = Instruction {
+    name: "toggle",
+    effect: Effect::Toggle,
+    ..DO
+};
+
+#[derive(Clone, Debug)]
+pub struct InstructionSet {
+    // earlier instructions win if two could match at the same spot
+    instructions: Vec<Instruction>,
+}
+
+impl InstructionSet {
+    pub fn new(instructions: Vec<Instruction>) -> InstructionSet {
+        for instruction in &instructions {
+            assert!(
+                !instruction.name.is_empty() && instruction.arity <= MAX_ARITY,
+                "{:?} needs a name and at most {} operands",
+                instruction.name,
+                MAX_ARITY
+            );
+        }
+        InstructionSet { instructions }
+    }
+
+    pub fn part1() -> InstructionSet {
+        InstructionSet::new(vec![MUL])
+    }
+
+    pub fn part2() -> InstructionSet {
+        InstructionSet::new(vec![MUL, DO, DONT])
+    }
+
+    pub fn extended() -> InstructionSet {
+        InstructionSet::new(vec![MUL, ADD, SUB, DO, DONT, TOGGLE])
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub instruction: &'a Instruction,
+    pub operands: Vec<i64>,
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[derive(Debug)]
+pub struct Machine {
+    pub total: i64,
+    pub enabled: bool,
+}
+
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine {
+            total: 0,
+            enabled: true,
+        }
+    }
+}
+
+impl Machine {
+    // Err if the result or the new total doesn't fit in an i64, and then the total stays put
+    pub fn execute(&mut self, token: &Token) -> Result<(), String> {
+        match token.instruction.effect {
+            Effect::Accumulate(f) => {
+                if self.enabled {
+                    self.total = f(&token.operands)
+                        .and_then(|result| self.total.checked_add(result))
+                        .ok_or_else(|| {
+                            format!(
+                                "{} at offset {} overflows the total",
+                                token.instruction.name, token.offset
+                            )
+                        })?;
+                }
+            }
+            Effect::Enable => self.enabled = true,
+            Effect::Disable => self.enabled = false,
+            Effect::Toggle => self.enabled = !self.enabled,
+        }
+        Ok(())
+    }
+}
+
+pub fn run<R: Read>(set: &InstructionSet, memory: R) -> io::Result<Machine> {
+    let mut machine = Machine::default();
+    for token in Tokens::new(set, memory) {
+        machine
+            .execute(&token?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    }
+    Ok(machine)
+}
@@ -1,54 +1,107 @@
-use regex::Regex;
+mod interpreter;
+mod tokenizer;
+mod trace;
+
+use interpreter::InstructionSet;
+use std::env;
 use std::fs;
 
 fn main() {
     let input = fs::read_to_string("input.txt").unwrap();
 
+    // cargo run -- [trace | highlight] [--part1 | --extended]
+    // --extended runs the memory with every instruction we know about (add, sub, toggle, ...)
+    let args: Vec<String> = env::args().skip(1).collect();
+    let has_arg = |arg: &str| args.iter().any(|a| a == arg);
+    let set = if has_arg("--part1") {
+        InstructionSet::part1()
+    } else if has_arg("--extended") {
+        InstructionSet::extended()
+    } else {
+        InstructionSet::part2()
+    };
+    if has_arg("trace") {
+        print!(
+            "{}",
+            trace::format_trace(&trace::trace(&set, input.as_bytes()), input.as_bytes())
+        );
+        return;
+    }
+    if has_arg("highlight") {
+        println!(
+            "{}",
+            trace::highlight(&trace::trace(&set, input.as_bytes()), input.as_bytes())
+        );
+        return;
+    }
+    if has_arg("--extended") {
+        match interpreter::run(&set, input.as_bytes()) {
+            Ok(machine) => println!("extended: {}", machine.total),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
     println!("part 1: {}", part1(&input));
     println!("part 2: {}", part2(&input));
 }
 
-fn part1(input: &str) -> i32 {
-    let re = Regex::new(r"mul\([0-9]{1,3},[0-9]{1,3}\)").unwrap();
-    re.find_iter(input)
-        .map(|m| m.as_str())
-        .fold(0, |acc, s| acc + mul(s))
+// part 1 and part 2 are the same machine, just with different instructions
+fn part1(input: &str) -> i64 {
+    interpreter::run(&InstructionSet::part1(), input.as_bytes())
+        .unwrap()
+        .total
 }
 
-fn mul(mul_str: &str) -> i32 {
-    let re = Regex::new(r"[0-9]{1,3}").unwrap();
-    re.find_iter(mul_str)
-        .map(|m| m.as_str())
-        .fold(1, |acc, s| acc * s.parse::<i32>().unwrap())
-}
-
-fn part2(input: &str) -> i32 {
-    let re = Regex::new(r"(mul\([0-9]{1,3},[0-9]{1,3}\))|(do\(\))|(don't\(\))").unwrap();
-    let mut enabled = true;
-    re.find_iter(input).map(|m| m.as_str()).fold(0, |acc, s| {
-        acc + match s {
-            "do()" => {
-                enabled = true;
-                0
-            }
-            "don't()" => {
-                enabled = false;
-                0
-            }
-            _ => {
-                if enabled {
-                    mul(s)
-                } else {
-                    0
-                }
-            }
-        }
-    })
+fn part2(input: &str) -> i64 {
+    interpreter::run(&InstructionSet::part2(), input.as_bytes())
+        .unwrap()
+        .total
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use regex::Regex;
+
+    // the original regex scanner, kept to check the tokenizer against
+    fn regex_part1(input: &str) -> i32 {
+        let re = Regex::new(r"mul\([0-9]{1,3},[0-9]{1,3}\)").unwrap();
+        re.find_iter(input)
+            .map(|m| m.as_str())
+            .fold(0, |acc, s| acc + regex_mul(s))
+    }
+
+    fn regex_mul(mul_str: &str) -> i32 {
+        let re = Regex::new(r"[0-9]{1,3}").unwrap();
+        re.find_iter(mul_str)
+            .map(|m| m.as_str())
+            .fold(1, |acc, s| acc * s.parse::<i32>().unwrap())
+    }
+
+    fn regex_part2(input: &str) -> i32 {
+        let re = Regex::new(r"(mul\([0-9]{1,3},[0-9]{1,3}\))|(do\(\))|(don't\(\))").unwrap();
+        let mut enabled = true;
+        re.find_iter(input).map(|m| m.as_str()).fold(0, |acc, s| {
+            acc + match s {
+                "do()" => {
+                    enabled = true;
+                    0
+                }
+                "don't()" => {
+                    enabled = false;
+                    0
+                }
+                _ => {
+                    if enabled {
+                        regex_mul(s)
+                    } else {
+                        0
+                    }
+                }
+            }
+        })
+    }
 
     fn get_input() -> String {
         fs::read_to_string("input.txt").unwrap()
@@ -65,4 +118,164 @@ mod tests {
         let result = part2(&get_input());
         assert_eq!(result, 75920122);
     }
+
+    #[test]
+    fn interpreter_with_extra_instructions() {
+        let memory = b"add(1,2)sub(10,4)toggle()mul(5,5)toggle(add(100,1)don't()toggle()mul(2,2)";
+        let extended = InstructionSet::extended();
+        let tokens: Vec<interpreter::Token> = tokenizer::Tokens::new(&extended, &memory[..])
+            .map(|t| t.unwrap())
+            .collect();
+        let names: Vec<&str> = tokens.iter().map(|t| t.instruction.name).collect();
+        assert_eq!(
+            names,
+            ["add", "sub", "toggle", "mul", "add", "don't", "toggle", "mul"]
+        );
+        assert_eq!(tokens[1].operands, [10, 4]);
+        assert_eq!(tokens[1].offset, 8);
+
+        // toggle( is malformed, so the add is still off. don't() then toggle() turns mul back on.
+        let machine = interpreter::run(&extended, &memory[..]).unwrap();
+        assert_eq!(machine.total, 3 + 6 + 4);
+        assert!(machine.enabled);
+    }
+
+    #[test]
+    fn numbers_too_big_for_an_i64() {
+        // 20 digits can't be an operand, so there's no instruction there at all
+        let long = interpreter::Instruction {
+            name: "mul",
+            max_digits: 25,
+            ..interpreter::MUL
+        };
+        let set = InstructionSet::new(vec![long]);
+        let memory = b"mul(99999999999999999999,1)mul(9,9)";
+        let tokens: Vec<interpreter::Token> = tokenizer::Tokens::new(&set, &memory[..])
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].operands, [9, 9]);
+
+        // both operands fit, but the product doesn't
+        let memory = b"mul(2,3)mul(9999999999,9999999999)mul(1,1)";
+        let err = interpreter::run(&set, &memory[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let statuses: Vec<(trace::Status, i64)> = trace::trace(&set, memory)
+            .iter()
+            .map(|e| (e.status, e.total))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                (trace::Status::Valid, 6),
+                (trace::Status::Overflowed, 6),
+                (trace::Status::Valid, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_statuses_on_toy_input() {
+        let memory = fs::read_to_string("toy_input_part2.txt").unwrap();
+        let set = InstructionSet::part2();
+        let entries = trace::trace(&set, memory.as_bytes());
+
+        let summary: Vec<(usize, trace::Status, i64)> = entries
+            .iter()
+            .map(|e| (e.offset, e.status, e.total))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, trace::Status::Valid, 8),
+                (10, trace::Status::Malformed, 8),
+                (20, trace::Status::Valid, 8),
+                (28, trace::Status::Disabled, 8),
+                (37, trace::Status::Malformed, 8),
+                (48, trace::Status::Disabled, 8),
+                (59, trace::Status::Valid, 8),
+                (64, trace::Status::Valid, 48),
+            ]
+        );
+        assert_eq!(entries.last().unwrap().total, regex_part2(&memory) as i64);
+    }
+
+    #[test]
+    fn highlight_dims_disabled_region() {
+        let memory = b"mul(1,2)don't()mul(3,4)do()";
+        let set = InstructionSet::part2();
+        let rendered = trace::highlight(&trace::trace(&set, memory), memory);
+        assert_eq!(
+            rendered,
+            "\x1b[0m\x1b[1;32mmul(1,2)\x1b[0mdon't()\x1b[0m\x1b[2mmul(3,4)do()\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn highlight_keeps_utf8_whole() {
+        let memory = "é mul(1,2) ☃ don't() ünd".as_bytes();
+        let set = InstructionSet::part2();
+        let rendered = trace::highlight(&trace::trace(&set, memory), memory);
+        assert_eq!(
+            rendered,
+            "\x1b[0mé \x1b[0m\x1b[1;32mmul(1,2)\x1b[0m ☃ don't()\x1b[0m\x1b[2m ünd\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn streaming_matches_regex_on_toy_input() {
+        for path in ["toy_input_part1.txt", "toy_input_part2.txt"] {
+            let input = fs::read_to_string(path).unwrap();
+            assert_eq!(part1(&input), regex_part1(&input) as i64);
+            assert_eq!(part2(&input), regex_part2(&input) as i64);
+        }
+    }
+
+    #[test]
+    fn streaming_across_buffer_boundaries() {
+        let input = "mulmul(1,2)mul(1234,5)mul(12,345)do(don't()mul(6,7)ddo()mul(3,3)mul(4,4";
+        let set = InstructionSet::part2();
+        let expected: Vec<interpreter::Token> = tokenizer::Tokens::new(&set, input.as_bytes())
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(expected.len(), 6);
+
+        for capacity in 1..input.len() {
+            let tokens: Vec<interpreter::Token> =
+                tokenizer::Tokens::with_capacity(&set, input.as_bytes(), capacity)
+                    .map(|t| t.unwrap())
+                    .collect();
+            assert_eq!(tokens, expected);
+        }
+        assert_eq!(part1(input), regex_part1(input) as i64);
+        assert_eq!(part2(input), regex_part2(input) as i64);
+    }
+
+    #[test]
+    fn leftmost_match_waits_for_earlier_starts() {
+        // do() finishes first, but do()do( started at the same spot and comes first in the set,
+        // so do() can't come out until that dies at the n
+        let do_twice = interpreter::Instruction {
+            name: "do()do",
+            ..interpreter::DO
+        };
+        let set = InstructionSet::new(vec![do_twice, interpreter::DO, interpreter::DONT]);
+        let tokens: Vec<(&str, usize)> = tokenizer::Tokens::new(&set, &b"do()don't()"[..])
+            .map(|t| t.unwrap())
+            .map(|t| (t.instruction.name, t.offset))
+            .collect();
+        assert_eq!(tokens, [("do", 0), ("don't", 4)]);
+
+        // and when both finish, the one that started first wins
+        let xmul = interpreter::Instruction {
+            name: "xmul",
+            ..interpreter::MUL
+        };
+        let set = InstructionSet::new(vec![interpreter::MUL, xmul]);
+        let tokens: Vec<(&str, usize)> = tokenizer::Tokens::new(&set, &b"xmul(1,2)mul(3,4)"[..])
+            .map(|t| t.unwrap())
+            .map(|t| (t.instruction.name, t.offset))
+            .collect();
+        assert_eq!(tokens, [("xmul", 0), ("mul", 9)]);
+    }
 }
@@ -0,0 +1,230 @@
+use crate::interpreter::{Instruction, InstructionSet, Token};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+// the most operands any instruction can take, so a candidate's operands fit in an array and
+// starting one doesn't allocate
+pub const MAX_ARITY: usize = 4;
+
+// how far into one instruction we've read
+#[derive(Clone, Copy, Debug)]
+enum Progress {
+    // this many bytes of the name. once the whole name is in, we need the '('
+    Name(usize),
+    // reading operand number count
+    Operand {
+        operands: [i64; MAX_ARITY],
+        count: usize,
+        value: i64,
+        digits: usize,
+    },
+    // no operands, just waiting on the ')'
+    Close,
+    // read the whole thing, ending just before end
+    Done {
+        operands: [i64; MAX_ARITY],
+        count: usize,
+        end: usize,
+    },
+}
+
+// moves progress on by one byte, false if the byte kills it
+fn step(instruction: &Instruction, progress: &mut Progress, byte: u8, pos: usize) -> bool {
+    let name = instruction.name.as_bytes();
+    *progress = match *progress {
+        Progress::Name(n) if n < name.len() && byte == name[n] => Progress::Name(n + 1),
+        Progress::Name(n) if n < name.len() || byte != b'(' => return false,
+        Progress::Name(_) if instruction.arity == 0 => Progress::Close,
+        Progress::Name(_) => Progress::Operand {
+            operands: [0; MAX_ARITY],
+            count: 0,
+            value: 0,
+            digits: 0,
+        },
+        Progress::Operand {
+            mut operands,
+            count,
+            value,
+            digits,
+        } => match byte {
+            b'0'..=b'9' if digits < instruction.max_digits => {
+                // max_digits is only data, a number too big for an i64 just isn't one
+                let Some(value) = value
+                    .checked_mul(10)
+                    .and_then(|v| v.checked_add((byte - b'0') as i64))
+                else {
+                    return false;
+                };
+                Progress::Operand {
+                    operands,
+                    count,
+                    value,
+                    digits: digits + 1,
+                }
+            }
+            b',' | b')' if digits >= instruction.min_digits => {
+                operands[count] = value;
+                match (byte, count + 1 == instruction.arity) {
+                    (b',', false) => Progress::Operand {
+                        operands,
+                        count: count + 1,
+                        value: 0,
+                        digits: 0,
+                    },
+                    (b')', true) => Progress::Done {
+                        operands,
+                        count: count + 1,
+                        end: pos + 1,
+                    },
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        },
+        Progress::Close if byte == b')' => Progress::Done {
+            operands: [0; MAX_ARITY],
+            count: 0,
+            end: pos + 1,
+        },
+        Progress::Close => return false,
+        Progress::Done { .. } => return true,
+    };
+    true
+}
+
+#[derive(Debug)]
+struct Candidate<'a> {
+    instruction: &'a Instruction,
+    start: usize,
+    progress: Progress,
+}
+
+// byte-at-a-time, so it doesn't care where the input gets split up. every instruction that could
+// still match is followed at once, and a finished one only comes out once nothing that started
+// earlier (or earlier in the set, at the same spot) can still beat it. leftmost, non-overlapping,
+// same as the regex version.
+#[derive(Debug)]
+pub struct Scanner<'a> {
+    set: &'a InstructionSet,
+    // in order of start, then order in the set
+    candidates: VecDeque<Candidate<'a>>,
+    pos: usize,
+    ready: VecDeque<Token<'a>>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(set: &'a InstructionSet) -> Scanner<'a> {
+        Scanner {
+            set,
+            candidates: VecDeque::new(),
+            pos: 0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    pub fn feed(&mut self, byte: u8) {
+        let pos = self.pos;
+        self.candidates
+            .retain_mut(|c| step(c.instruction, &mut c.progress, byte, pos));
+        // a new one only gets going if this is the first letter of its name
+        for instruction in self.set.instructions() {
+            if instruction.name.as_bytes().first() == Some(&byte) {
+                self.candidates.push_back(Candidate {
+                    instruction,
+                    start: pos,
+                    progress: Progress::Name(1),
+                });
+            }
+        }
+        self.pos += 1;
+        self.release();
+    }
+
+    // end of the input, anything unfinished never will be
+    pub fn finish(&mut self) {
+        self.candidates
+            .retain(|c| matches!(c.progress, Progress::Done { .. }));
+        self.release();
+    }
+
+    pub fn next_token(&mut self) -> Option<Token<'a>> {
+        self.ready.pop_front()
+    }
+
+    fn release(&mut self) {
+        while let Some(Candidate {
+            instruction,
+            start,
+            progress:
+                Progress::Done {
+                    operands,
+                    count,
+                    end,
+                },
+        }) = self.candidates.front()
+        {
+            self.ready.push_back(Token {
+                instruction,
+                operands: operands[..*count].to_vec(),
+                offset: *start,
+                len: end - start,
+            });
+            // nothing can overlap it, and everything that started inside it is at the front
+            let end = *end;
+            while self.candidates.front().is_some_and(|c| c.start < end) {
+                self.candidates.pop_front();
+            }
+        }
+    }
+}
+
+// pulls tokens out of a reader, one buffer at a time
+pub struct Tokens<'a, R: Read> {
+    reader: R,
+    scanner: Scanner<'a>,
+    buf: Vec<u8>,
+    finished: bool,
+}
+
+impl<'a, R: Read> Tokens<'a, R> {
+    pub fn new(set: &'a InstructionSet, reader: R) -> Tokens<'a, R> {
+        Tokens::with_capacity(set, reader, 8 * 1024)
+    }
+
+    pub fn with_capacity(set: &'a InstructionSet, reader: R, capacity: usize) -> Tokens<'a, R> {
+        Tokens {
+            reader,
+            scanner: Scanner::new(set),
+            buf: vec![0; capacity],
+            finished: false,
+        }
+    }
+}
+
+impl<'a, R: Read> Iterator for Tokens<'a, R> {
+    type Item = io::Result<Token<'a>>;
+
+    fn next(&mut self) -> Option<io::Result<Token<'a>>> {
+        loop {
+            if let Some(token) = self.scanner.next_token() {
+                return Some(Ok(token));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.reader.read(&mut self.buf) {
+                Ok(0) => {
+                    self.scanner.finish();
+                    self.finished = true;
+                }
+                Ok(n) => {
+                    for &byte in &self.buf[..n] {
+                        self.scanner.feed(byte);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::interpreter::{Effect, Instruction, InstructionSet, Machine};
+use crate::tokenizer::Tokens;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Valid,
+    // the name was there, but the rest of it wasn't
+    Malformed,
+    // well-formed, but skipped because of a don't()
+    Disabled,
+    // would have run, but the total can't hold the result
+    Overflowed,
+}
+
+#[derive(Debug)]
+pub struct TraceEntry<'a> {
+    pub offset: usize,
+    pub len: usize,
+    pub instruction: &'a Instruction,
+    pub status: Status,
+    pub total: i64,
+}
+
+// every spot where an instruction name shows up, whether or not it ran
+pub fn trace<'a>(set: &'a InstructionSet, memory: &[u8]) -> Vec<TraceEntry<'a>> {
+    let mut machine = Machine::default();
+    let mut entries = Vec::new();
+
+    // the tokenizer finds the ones that ran, and anywhere in between that has a name is malformed
+    let mut pos = 0;
+    for token in Tokens::new(set, memory) {
+        let token = token.unwrap();
+        malformed(set, memory, pos..token.offset, machine.total, &mut entries);
+
+        let status = match token.instruction.effect {
+            Effect::Accumulate(_) if !machine.enabled => Status::Disabled,
+            _ => match machine.execute(&token) {
+                Ok(()) => Status::Valid,
+                Err(_) => Status::Overflowed,
+            },
+        };
+        entries.push(TraceEntry {
+            offset: token.offset,
+            len: token.len,
+            instruction: token.instruction,
+            status,
+            total: machine.total,
+        });
+        pos = token.offset + token.len;
+    }
+    malformed(set, memory, pos..memory.len(), machine.total, &mut entries);
+
+    entries
+}
+
+fn malformed<'a>(
+    set: &'a InstructionSet,
+    memory: &[u8],
+    gap: Range<usize>,
+    total: i64,
+    entries: &mut Vec<TraceEntry<'a>>,
+) {
+    for pos in gap {
+        let named = set
+            .instructions()
+            .iter()
+            .filter(|i| memory[pos..].starts_with(i.name.as_bytes()))
+            .max_by_key(|i| i.name.len());
+        if let Some(instruction) = named {
+            entries.push(TraceEntry {
+                offset: pos,
+                len: instruction.name.len(),
+                instruction,
+                status: Status::Malformed,
+                total,
+            });
+        }
+    }
+}
+
+pub fn format_trace(entries: &[TraceEntry], memory: &[u8]) -> String {
+    entries
+        .iter()
+        .map(|e| {
+            let status = match e.status {
+                Status::Valid => "valid",
+                Status::Malformed => "malformed",
+                Status::Disabled => "disabled",
+                Status::Overflowed => "overflowed",
+            };
+            format!(
+                "{:>8}  {:<16}  {:<9}  total {}\n",
+                e.offset,
+                snippet(e, memory),
+                status,
+                e.total
+            )
+        })
+        .collect()
+}
+
+// the instruction itself, or for malformed ones, a few bytes past the name to show what went wrong
+fn snippet(entry: &TraceEntry, memory: &[u8]) -> String {
+    let end = match entry.status {
+        Status::Malformed => (entry.offset + entry.len + 8).min(memory.len()),
+        _ => entry.offset + entry.len,
+    };
+    String::from_utf8_lossy(&memory[entry.offset..end])
+        .replace(['\n', '\r'], " ")
+        .to_string()
+}
+
+const GREEN: &str = "\x1b[1;32m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+// counted instructions in green, everything while disabled dimmed
+pub fn highlight(entries: &[TraceEntry], memory: &[u8]) -> String {
+    let mut styles: Vec<&str> = vec![""; memory.len()];
+    let mut enabled = true;
+    let mut last = 0;
+    for e in entries.iter().filter(|e| e.status != Status::Malformed) {
+        let style = if enabled { "" } else { DIM };
+        styles[last..e.offset].fill(style);
+
+        let instruction_style = match (e.instruction.effect, e.status) {
+            (Effect::Accumulate(_), Status::Valid) => GREEN,
+            _ => style,
+        };
+        styles[e.offset..e.offset + e.len].fill(instruction_style);
+        last = e.offset + e.len;
+
+        match e.instruction.effect {
+            Effect::Enable => enabled = true,
+            Effect::Disable => enabled = false,
+            Effect::Toggle => enabled = !enabled,
+            Effect::Accumulate(_) => {}
+        }
+    }
+    styles[last..].fill(if enabled { "" } else { DIM });
+
+    // a run of bytes at a time, so multi-byte UTF-8 comes out whole. runs only break at an
+    // instruction, and those start and end on ASCII.
+    let mut out = String::new();
+    let mut start = 0;
+    while start < memory.len() {
+        let style = styles[start];
+        let len = styles[start..].iter().take_while(|&&s| s == style).count();
+        out.push_str(RESET);
+        out.push_str(style);
+        out.push_str(&String::from_utf8_lossy(&memory[start..start + len]));
+        start += len;
+    }
+    out.push_str(RESET);
+    out
+}
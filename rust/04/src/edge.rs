@@ -0,0 +1,44 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgePolicy {
+    // off the edge is off the board
+    Bounded,
+    // torus: off the right comes back on the left
+    Wrap,
+    // bounce off the edge like a mirror, without reading the edge cell twice
+    Reflect,
+}
+
+impl EdgePolicy {
+    pub fn from_arg(arg: &str) -> Result<EdgePolicy, String> {
+        match arg {
+            "bounded" => Ok(EdgePolicy::Bounded),
+            "wrap" => Ok(EdgePolicy::Wrap),
+            "reflect" => Ok(EdgePolicy::Reflect),
+            _ => Err(format!(
+                "--edges wants bounded, wrap or reflect, got {:?}",
+                arg
+            )),
+        }
+    }
+
+    // where coordinate x lands on an axis of length n, None if it's off the grid
+    pub fn fold(&self, x: i32, n: usize) -> Option<usize> {
+        let n = n as i32;
+        match self {
+            EdgePolicy::Bounded => (0..n).contains(&x).then_some(x as usize),
+            EdgePolicy::Wrap => Some(x.rem_euclid(n) as usize),
+            EdgePolicy::Reflect => {
+                if n == 1 {
+                    return Some(0);
+                }
+                let period = 2 * (n - 1);
+                let m = x.rem_euclid(period);
+                Some(if m < n { m } else { period - m } as usize)
+            }
+        }
+    }
+
+    pub fn cell(&self, grid: &[Vec<char>], r: i32, c: i32) -> Option<(usize, usize)> {
+        Some((self.fold(r, grid.len())?, self.fold(c, grid[0].len())?))
+    }
+}
@@ -1,8 +1,89 @@
-use std::fs;
+mod edge;
+mod render;
+mod template;
+mod word_search;
+
+use edge::EdgePolicy;
+use std::{env, fs};
+use template::{Template, Transforms};
+use word_search::WordSearch;
 
 fn main() {
     let grid = parse_grid(false);
 
+    // every mode takes --edges=bounded | wrap | reflect, defaulting to bounded
+    let args: Vec<String> = env::args().skip(1).collect();
+    let edges = match args
+        .iter()
+        .find_map(|a| a.strip_prefix("--edges="))
+        .map_or(Ok(EdgePolicy::Bounded), EdgePolicy::from_arg)
+    {
+        Ok(edges) => edges,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    // cargo run -- search XMAS SAMX ... [--render=ansi | dots | heat]
+    if args.first().is_some_and(|a| a == "search") {
+        let words: Vec<&str> = args[1..]
+            .iter()
+            .filter(|w| !w.starts_with("--"))
+            .map(|w| w.as_str())
+            .collect();
+        let search = WordSearch::new(&words);
+        let matches = search.find_all(&grid, edges);
+        let counts = render::coverage(&grid, &search, &matches, edges);
+        match args.iter().find_map(|a| a.strip_prefix("--render=")) {
+            Some("ansi") => print!("{}", render::ansi(&grid, &counts)),
+            Some("dots") => print!("{}", render::dots(&grid, &counts)),
+            Some("heat") => print!("{}", render::heat_map(&grid, &counts)),
+            Some(_) => panic!("unknown render mode"),
+            None => {
+                for m in &matches {
+                    let word: String = search.word(m.word).iter().collect();
+                    println!("{} at ({}, {}) heading {:?}", word, m.r, m.c, m.direction);
+                }
+            }
+        }
+        println!("{} matches", matches.len());
+        return;
+    }
+
+    // cargo run -- shape x-mas | plus-mas | 'M.S/.A./M.S' [--fixed | --reflect]
+    // '.' matches anything, '/' separates rows. rotations are on unless --fixed.
+    if args.first().is_some_and(|a| a == "shape") {
+        let pattern = match args
+            .get(1)
+            .filter(|a| !a.starts_with("--"))
+            .map(|a| a.as_str())
+        {
+            Some("x-mas") | None => template::X_MAS.to_string(),
+            Some("plus-mas") => template::PLUS_MAS.to_string(),
+            Some(p) => p.replace('/', "\n"),
+        };
+        let transforms = if args.iter().any(|a| a == "--fixed") {
+            Transforms::NONE
+        } else if args.iter().any(|a| a == "--reflect") {
+            Transforms::ALL
+        } else {
+            Transforms::ROTATIONS
+        };
+        let matches = template::find_shapes(&grid, &Template::parse(&pattern), transforms, edges);
+        println!("{} shapes", matches.len());
+        return;
+    }
+
+    if edges != EdgePolicy::Bounded {
+        let xmas = WordSearch::new(&["XMAS"]).find_all(&grid, edges);
+        let x_mas = Template::parse(template::X_MAS);
+        let crossings = template::find_shapes(&grid, &x_mas, Transforms::ROTATIONS, edges);
+        println!("part1 ({:?}): {}", edges, xmas.len());
+        println!("part2 ({:?}): {}", edges, crossings.len());
+        return;
+    }
+
     println!("part1: {}", part1(&grid));
     println!("part2: {}", part2(&grid));
     println!("part2 again: {}", part2_functional(&grid));
@@ -14,87 +95,18 @@ fn parse_grid(toy: bool) -> Vec<Vec<char>> {
     input.lines().map(|l| l.chars().collect()).collect()
 }
 
-fn part1(grid: &Vec<Vec<char>>) -> i32 {
-    let mut xmas_count = 0;
-    let xmas = ['X', 'M', 'A', 'S'];
-
-    for r in 0..grid.len() {
-        for c in 0..grid[r].len() {
-            xmas_count += search_all_dirs(grid, &xmas, r as i32, c as i32);
-        }
-    }
-    xmas_count
+fn part1(grid: &[Vec<char>]) -> i32 {
+    WordSearch::new(&["XMAS"])
+        .find_all(grid, EdgePolicy::Bounded)
+        .len() as i32
 }
 
-fn search_all_dirs(grid: &Vec<Vec<char>>, search_str: &[char], start_r: i32, start_c: i32) -> i32 {
-    if grid[start_r as usize][start_c as usize] != search_str[0] {
-        return 0;
-    }
-
-    let mut count = 0;
-    for r_move in -1..=1 {
-        for c_move in -1..=1 {
-            if r_move == 0 && c_move == 0 {
-                continue;
-            }
-            if search_direction(
-                grid,
-                &search_str[1..],
-                start_r + r_move,
-                start_c + c_move,
-                r_move,
-                c_move,
-            ) {
-                count += 1;
-            }
-        }
-    }
-    count
-}
-
-fn search_direction(
-    grid: &Vec<Vec<char>>,
-    search_str: &[char],
-    start_r: i32,
-    start_c: i32,
-    r_move: i32,
-    c_move: i32,
-) -> bool {
-    if search_str.is_empty() {
-        return true;
-    }
-    if start_r < 0
-        || start_r as usize >= grid.len()
-        || start_c < 0
-        || start_c as usize >= grid[0].len()
-    {
-        return false;
-    }
-    grid[start_r as usize][start_c as usize] == search_str[0]
-        && search_direction(
-            grid,
-            &search_str[1..],
-            start_r + r_move,
-            start_c + c_move,
-            r_move,
-            c_move,
-        )
+fn part2(grid: &[Vec<char>]) -> i32 {
+    let x_mas = Template::parse(template::X_MAS);
+    template::find_shapes(grid, &x_mas, Transforms::ROTATIONS, EdgePolicy::Bounded).len() as i32
 }
 
-fn part2(grid: &Vec<Vec<char>>) -> i32 {
-    let mut xmas_count = 0;
-
-    for r in 0..grid.len() {
-        for c in 0..grid[r].len() {
-            if check_mas_crossing(grid, r, c) {
-                xmas_count += 1;
-            }
-        }
-    }
-    xmas_count
-}
-
-fn part2_functional(grid: &Vec<Vec<char>>) -> i32 {
+fn part2_functional(grid: &[Vec<char>]) -> i32 {
     // it's functional... but really disfunctional
     (0..grid.len()).fold(0, |grid_acc, r| {
         grid_acc
@@ -104,7 +116,7 @@ fn part2_functional(grid: &Vec<Vec<char>>) -> i32 {
     })
 }
 
-fn check_mas_crossing(grid: &Vec<Vec<char>>, a_r: usize, a_c: usize) -> bool {
+fn check_mas_crossing(grid: &[Vec<char>], a_r: usize, a_c: usize) -> bool {
     if grid[a_r][a_c] != 'A' {
         return false;
     }
@@ -149,12 +161,228 @@ mod tests {
         assert_eq!(result, 2603);
     }
 
+    #[test]
+    fn part1_toy_answer() {
+        assert_eq!(part1(&parse_grid(true)), 18);
+    }
+
+    #[test]
+    fn word_search_matches() {
+        let grid = parse_grid(true);
+        let search = WordSearch::new(&["XMAS", "MAS", "SAMX", "AMM"]);
+        let matches = search.find_all(&grid, EdgePolicy::Bounded);
+
+        let count = |word| matches.iter().filter(|m| m.word == word).count();
+        // SAMX is XMAS backwards, so it's found the same number of times
+        assert_eq!(count(0), 18);
+        assert_eq!(count(2), 18);
+
+        // the XMAS along the top row
+        assert!(matches.contains(&word_search::Match {
+            word: 0,
+            r: 0,
+            c: 5,
+            direction: (0, 1),
+        }));
+        for m in &matches {
+            let word = search.word(m.word);
+            for (i, &ch) in word.iter().enumerate() {
+                let r = m.r as i32 + m.direction.0 * i as i32;
+                let c = m.c as i32 + m.direction.1 * i as i32;
+                assert_eq!(grid[r as usize][c as usize], ch);
+            }
+        }
+    }
+
     #[test]
     fn part2_answer() {
         let result = part2(&parse_grid(false));
         assert_eq!(result, 1965);
     }
 
+    #[test]
+    fn part2_toy_answer() {
+        assert_eq!(part2(&parse_grid(true)), 9);
+        assert_eq!(part2_functional(&parse_grid(true)), 9);
+    }
+
+    #[test]
+    fn template_orientations() {
+        // X-MAS has four distinct rotations, and reflecting doesn't add any new ones
+        let x_mas = Template::parse(template::X_MAS);
+        assert_eq!(x_mas.orientations(Transforms::ROTATIONS).len(), 4);
+        assert_eq!(x_mas.orientations(Transforms::ALL).len(), 4);
+
+        let l_shape = Template::parse("M.\nAS");
+        assert_eq!(l_shape.orientations(Transforms::NONE).len(), 1);
+        assert_eq!(l_shape.orientations(Transforms::ROTATIONS).len(), 4);
+        assert_eq!(l_shape.orientations(Transforms::ALL).len(), 8);
+    }
+
+    #[test]
+    fn empty_words_are_never_found() {
+        let grid: Vec<Vec<char>> = vec!["AB".chars().collect()];
+        let matches = WordSearch::new(&["", "A"]).find_all(&grid, EdgePolicy::Bounded);
+        // just the A, once for each direction that has room for a one letter word
+        assert_eq!(matches.len(), 8);
+        assert!(matches.iter().all(|m| m.word == 1 && (m.r, m.c) == (0, 0)));
+        assert!(WordSearch::new(&[""])
+            .find_all(&grid, EdgePolicy::Wrap)
+            .is_empty());
+    }
+
+    #[test]
+    fn plus_shaped_mas() {
+        let grid: Vec<Vec<char>> = ["XMXSX", "MASAM", "XSXMX", "XXXXX"]
+            .iter()
+            .map(|l| l.chars().collect())
+            .collect();
+        let plus = Template::parse(template::PLUS_MAS);
+        let matches = template::find_shapes(&grid, &plus, Transforms::ALL, EdgePolicy::Bounded);
+        // one centred on each A: (1, 1) and (1, 3)
+        let corners: Vec<(i32, i32)> = matches.iter().map(|m| (m.r, m.c)).collect();
+        assert_eq!(corners, [(0, 0), (0, 2)]);
+    }
+
+    #[test]
+    fn edge_policy_argument() {
+        assert_eq!(EdgePolicy::from_arg("wrap"), Ok(EdgePolicy::Wrap));
+        assert_eq!(EdgePolicy::from_arg("reflect"), Ok(EdgePolicy::Reflect));
+        for bad in ["", "Wrap", "torus"] {
+            assert!(EdgePolicy::from_arg(bad)
+                .unwrap_err()
+                .starts_with("--edges wants"));
+        }
+    }
+
+    #[test]
+    fn edge_policy_fold() {
+        let folded = |edges: EdgePolicy| -> Vec<Option<usize>> {
+            (-3..7).map(|x| edges.fold(x, 4)).collect()
+        };
+        assert_eq!(
+            folded(EdgePolicy::Bounded),
+            [
+                None,
+                None,
+                None,
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(3),
+                None,
+                None,
+                None
+            ]
+        );
+        assert_eq!(
+            folded(EdgePolicy::Wrap),
+            [1, 2, 3, 0, 1, 2, 3, 0, 1, 2].map(Some)
+        );
+        assert_eq!(
+            folded(EdgePolicy::Reflect),
+            [3, 2, 1, 0, 1, 2, 3, 2, 1, 0].map(Some)
+        );
+    }
+
+    #[test]
+    fn wrapped_word_search() {
+        let grid: Vec<Vec<char>> = ["ASXM", "....", "....", "...."]
+            .iter()
+            .map(|l| l.chars().collect())
+            .collect();
+        let search = WordSearch::new(&["XMAS"]);
+        assert!(search.find_all(&grid, EdgePolicy::Bounded).is_empty());
+
+        let wrapped = search.find_all(&grid, EdgePolicy::Wrap);
+        assert_eq!(
+            wrapped,
+            [word_search::Match {
+                word: 0,
+                r: 0,
+                c: 2,
+                direction: (0, 1),
+            }]
+        );
+
+        // XM then bouncing back off the right edge reads XMX, not XMAS
+        assert!(search.find_all(&grid, EdgePolicy::Reflect).is_empty());
+        let bounce: Vec<Vec<char>> = [".....", "..XMA", "....."]
+            .iter()
+            .map(|l| l.chars().collect())
+            .collect();
+        let reflected = WordSearch::new(&["XMAMX"]).find_all(&bounce, EdgePolicy::Reflect);
+        assert_eq!(
+            reflected,
+            [word_search::Match {
+                word: 0,
+                r: 1,
+                c: 2,
+                direction: (0, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn wrapped_shapes() {
+        // the A sits in the corner, so the X only exists on a torus
+        let grid: Vec<Vec<char>> = ["AXXXX", "XSXXM", "XXXXX", "XXXXX", "XSXXM"]
+            .iter()
+            .map(|l| l.chars().collect())
+            .collect();
+        let x_mas = Template::parse(template::X_MAS);
+        let find = |edges| template::find_shapes(&grid, &x_mas, Transforms::ROTATIONS, edges);
+        assert!(find(EdgePolicy::Bounded).is_empty());
+        assert_eq!(find(EdgePolicy::Wrap).len(), 1);
+    }
+
+    #[test]
+    fn reflected_shapes_bounce_off_every_edge() {
+        // the A is on the bottom edge, and the M.S row gets read twice on the way back up
+        let x_mas = Template::parse(template::X_MAS);
+        let find = |rows: &[&str]| {
+            let grid: Vec<Vec<char>> = rows.iter().map(|l| l.chars().collect()).collect();
+            template::find_shapes(&grid, &x_mas, Transforms::ROTATIONS, EdgePolicy::Reflect)
+        };
+        let bottom = find(&["XXX", "MXS", "XAX"]);
+        assert_eq!(bottom.len(), 1);
+        // same again upside down, bouncing off the top
+        let top = find(&["XAX", "MXS", "XXX"]);
+        assert_eq!(top.len(), 1);
+        assert!(top[0].r < 0);
+        // and turned on its side, off the left and right edges
+        assert_eq!(find(&["XMX", "AXX", "XSX"]).len(), 1);
+        assert_eq!(find(&["XMX", "XXA", "XSX"]).len(), 1);
+    }
+
+    #[test]
+    fn render_toy_matches() {
+        let grid = parse_grid(true);
+        let search = WordSearch::new(&["XMAS"]);
+        let matches = search.find_all(&grid, EdgePolicy::Bounded);
+        let counts = render::coverage(&grid, &search, &matches, EdgePolicy::Bounded);
+
+        // the puzzle's own example of the toy grid with everything else blanked out
+        let expected = "\
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+";
+        assert_eq!(render::dots(&grid, &counts), expected);
+
+        let total: usize = counts.iter().flatten().sum();
+        assert_eq!(total, 18 * 4);
+        let heat = render::heat_map(&grid, &counts);
+        assert!(heat.starts_with("....1111"));
+    }
+
     #[test]
     fn part2_functional_answer() {
         let result = part2_functional(&parse_grid(false));
@@ -0,0 +1,64 @@
+use crate::edge::EdgePolicy;
+use crate::word_search::{Match, WordSearch};
+
+const HIGHLIGHT: &str = "\x1b[1;33m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+// how many matches use each cell
+pub fn coverage(
+    grid: &[Vec<char>],
+    search: &WordSearch,
+    matches: &[Match],
+    edges: EdgePolicy,
+) -> Vec<Vec<usize>> {
+    let mut counts: Vec<Vec<usize>> = grid.iter().map(|row| vec![0; row.len()]).collect();
+    for m in matches {
+        for (r, c) in search.cells(grid, m, edges) {
+            counts[r][c] += 1;
+        }
+    }
+    counts
+}
+
+// matched cells in bold yellow, everything else dimmed
+pub fn ansi(grid: &[Vec<char>], counts: &[Vec<usize>]) -> String {
+    render(grid, counts, |ch, count| {
+        let style = if count > 0 { HIGHLIGHT } else { DIM };
+        format!("{}{}{}", style, ch, RESET)
+    })
+}
+
+// like the puzzle's example, where anything not part of a match is a '.'
+pub fn dots(grid: &[Vec<char>], counts: &[Vec<usize>]) -> String {
+    render(grid, counts, |ch, count| {
+        if count > 0 { ch } else { '.' }.to_string()
+    })
+}
+
+// number of matches through each cell, '+' once it's past 9
+pub fn heat_map(grid: &[Vec<char>], counts: &[Vec<usize>]) -> String {
+    render(grid, counts, |_, count| match count {
+        0 => ".".to_string(),
+        1..=9 => count.to_string(),
+        _ => "+".to_string(),
+    })
+}
+
+fn render(
+    grid: &[Vec<char>],
+    counts: &[Vec<usize>],
+    cell: impl Fn(char, usize) -> String,
+) -> String {
+    grid.iter()
+        .zip(counts)
+        .map(|(row, row_counts)| {
+            let line: String = row
+                .iter()
+                .zip(row_counts)
+                .map(|(&ch, &count)| cell(ch, count))
+                .collect();
+            line + "\n"
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+use crate::edge::EdgePolicy;
+use std::collections::HashSet;
+
+// a small grid of characters to look for, where '.' matches anything
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    cells: Vec<Vec<Option<char>>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Transforms {
+    pub rotate: bool,
+    pub reflect: bool,
+}
+
+impl Transforms {
+    pub const NONE: Transforms = Transforms {
+        rotate: false,
+        reflect: false,
+    };
+    pub const ROTATIONS: Transforms = Transforms {
+        rotate: true,
+        reflect: false,
+    };
+    pub const ALL: Transforms = Transforms {
+        rotate: true,
+        reflect: true,
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeMatch {
+    // top left corner of the template in the grid. only negative under Reflect, for a shape
+    // that bounces off the top or left edge.
+    pub r: i32,
+    pub c: i32,
+    // index into orientations()
+    pub orientation: usize,
+}
+
+pub const X_MAS: &str = "M.S\n.A.\nM.S";
+pub const PLUS_MAS: &str = ".M.\nMAS\n.S.";
+
+impl Template {
+    pub fn parse(s: &str) -> Template {
+        Template {
+            cells: s
+                .lines()
+                .map(|l| {
+                    l.chars()
+                        .map(|c| if c == '.' { None } else { Some(c) })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    pub fn cell(&self, r: usize, c: usize) -> Option<char> {
+        self.cells[r].get(c).copied().flatten()
+    }
+
+    // quarter turn clockwise
+    fn rotate(&self) -> Template {
+        Template {
+            cells: (0..self.width())
+                .map(|c| (0..self.height()).rev().map(|r| self.cell(r, c)).collect())
+                .collect(),
+        }
+    }
+
+    // mirror left to right
+    fn reflect(&self) -> Template {
+        Template {
+            cells: (0..self.height())
+                .map(|r| (0..self.width()).rev().map(|c| self.cell(r, c)).collect())
+                .collect(),
+        }
+    }
+
+    // every distinct way the template can be laid down. symmetric templates don't get counted twice.
+    pub fn orientations(&self, transforms: Transforms) -> Vec<Template> {
+        let mut bases = vec![self.clone()];
+        if transforms.reflect {
+            bases.push(self.reflect());
+        }
+
+        let mut orientations: Vec<Template> = Vec::new();
+        for base in bases {
+            let mut cur = base;
+            for _ in 0..if transforms.rotate { 4 } else { 1 } {
+                if !orientations.contains(&cur) {
+                    orientations.push(cur.clone());
+                }
+                cur = cur.rotate();
+            }
+        }
+        orientations
+    }
+
+    // the grid cell under each letter of the template, or None if it doesn't match
+    fn matches_at(
+        &self,
+        grid: &[Vec<char>],
+        r: i32,
+        c: i32,
+        edges: EdgePolicy,
+    ) -> Option<Vec<(usize, usize)>> {
+        let mut cells = Vec::new();
+        for tr in 0..self.height() {
+            for tc in 0..self.width() {
+                if let Some(ch) = self.cell(tr, tc) {
+                    let (gr, gc) = edges.cell(grid, r + tr as i32, c + tc as i32)?;
+                    if grid[gr][gc] != ch {
+                        return None;
+                    }
+                    cells.push((gr, gc));
+                }
+            }
+        }
+        Some(cells)
+    }
+}
+
+pub fn find_shapes(
+    grid: &[Vec<char>],
+    template: &Template,
+    transforms: Transforms,
+    edges: EdgePolicy,
+) -> Vec<ShapeMatch> {
+    let orientations = template.orientations(transforms);
+    let mut matches = Vec::new();
+    // under Reflect a shape can bounce off the top or left edge as well as the bottom or right,
+    // so it can start up to its own size off the grid. the same cells can come up again from
+    // another corner or orientation, and only count once.
+    let reflect = edges == EdgePolicy::Reflect;
+    let overhang = |len: usize| if reflect { len as i32 - 1 } else { 0 };
+    let above = orientations
+        .iter()
+        .map(|t| overhang(t.height()))
+        .max()
+        .unwrap_or(0);
+    let left = orientations
+        .iter()
+        .map(|t| overhang(t.width()))
+        .max()
+        .unwrap_or(0);
+    let mut placements: HashSet<Vec<(usize, usize)>> = HashSet::new();
+    for r in -above..grid.len() as i32 {
+        for c in -left..grid.first().map_or(0, |row| row.len()) as i32 {
+            for (orientation, t) in orientations.iter().enumerate() {
+                if r < -overhang(t.height()) || c < -overhang(t.width()) {
+                    continue;
+                }
+                let Some(mut cells) = t.matches_at(grid, r, c, edges) else {
+                    continue;
+                };
+                cells.sort();
+                if reflect && !placements.insert(cells) {
+                    continue;
+                }
+                matches.push(ShapeMatch { r, c, orientation });
+            }
+        }
+    }
+    matches
+}
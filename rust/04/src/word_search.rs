@@ -0,0 +1,212 @@
+use crate::edge::EdgePolicy;
+use std::collections::{HashMap, VecDeque};
+
+// (r_move, c_move), same as the old search_all_dirs loop
+pub const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Match {
+    // index into the words the search was built with
+    pub word: usize,
+    pub r: usize,
+    pub c: usize,
+    pub direction: (i32, i32),
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    next: HashMap<char, usize>,
+    fail: usize,
+    // every word that ends here, including ones that are suffixes of this node's string
+    outputs: Vec<usize>,
+}
+
+// Aho-Corasick automaton, so each line of the grid gets read once no matter how many words
+#[derive(Debug)]
+pub struct WordSearch {
+    words: Vec<Vec<char>>,
+    nodes: Vec<Node>,
+}
+
+impl WordSearch {
+    pub fn new(words: &[&str]) -> WordSearch {
+        let mut search = WordSearch {
+            words: words.iter().map(|w| w.chars().collect()).collect(),
+            nodes: vec![Node::default()],
+        };
+
+        for (i, word) in search.words.iter().enumerate() {
+            // an empty word would end at the root and get copied into every node down the fail
+            // links. it keeps its index so the others line up, but it's never found.
+            if word.is_empty() {
+                continue;
+            }
+            let mut cur = 0;
+            for &ch in word {
+                cur = match search.nodes[cur].next.get(&ch) {
+                    Some(&n) => n,
+                    None => {
+                        search.nodes.push(Node::default());
+                        let n = search.nodes.len() - 1;
+                        search.nodes[cur].next.insert(ch, n);
+                        n
+                    }
+                };
+            }
+            search.nodes[cur].outputs.push(i);
+        }
+
+        // breadth first, so a node's fail link is always finished before its children need it.
+        // the root's children fail back to the root, which is the default.
+        let mut queue: VecDeque<usize> = search.nodes[0].next.values().copied().collect();
+        while let Some(cur) = queue.pop_front() {
+            let children: Vec<(char, usize)> = search.nodes[cur]
+                .next
+                .iter()
+                .map(|(&ch, &n)| (ch, n))
+                .collect();
+            for (ch, child) in children {
+                let fail = search.follow(search.nodes[cur].fail, ch);
+                search.nodes[child].fail = fail;
+                let inherited = search.nodes[fail].outputs.clone();
+                search.nodes[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        search
+    }
+
+    pub fn word(&self, index: usize) -> &[char] {
+        &self.words[index]
+    }
+
+    // the cells a match covers, in word order
+    pub fn cells(&self, grid: &[Vec<char>], m: &Match, edges: EdgePolicy) -> Vec<(usize, usize)> {
+        (0..self.words[m.word].len() as i32)
+            .map(|i| {
+                edges
+                    .cell(
+                        grid,
+                        m.r as i32 + m.direction.0 * i,
+                        m.c as i32 + m.direction.1 * i,
+                    )
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    // where we end up after reading ch from state
+    fn follow(&self, mut state: usize, ch: char) -> usize {
+        loop {
+            if let Some(&n) = self.nodes[state].next.get(&ch) {
+                return n;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.nodes[state].fail;
+        }
+    }
+
+    pub fn find_all(&self, grid: &[Vec<char>], edges: EdgePolicy) -> Vec<Match> {
+        DIRECTIONS
+            .iter()
+            .flat_map(|&direction| {
+                let lines = match edges {
+                    EdgePolicy::Bounded => lines(grid, direction),
+                    _ => self.windows(grid, direction, edges),
+                };
+                lines.into_iter().flat_map(move |(line, starts)| {
+                    self.find_in_line(grid, &line, starts, direction)
+                })
+            })
+            .collect()
+    }
+
+    // only matches starting in the first `starts` cells of the line count
+    fn find_in_line(
+        &self,
+        grid: &[Vec<char>],
+        line: &[(usize, usize)],
+        starts: usize,
+        direction: (i32, i32),
+    ) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut state = 0;
+        for (i, &(r, c)) in line.iter().enumerate() {
+            state = self.follow(state, grid[r][c]);
+            for &word in &self.nodes[state].outputs {
+                let start = i + 1 - self.words[word].len();
+                if start >= starts {
+                    continue;
+                }
+                let (start_r, start_c) = line[start];
+                matches.push(Match {
+                    word,
+                    r: start_r,
+                    c: start_c,
+                    direction,
+                });
+            }
+        }
+        matches
+    }
+
+    // when the edges don't stop us, lines never end. instead read just far enough
+    // from every cell to fit the longest word, and only count words starting there.
+    fn windows(
+        &self,
+        grid: &[Vec<char>],
+        direction: (i32, i32),
+        edges: EdgePolicy,
+    ) -> Vec<(Vec<(usize, usize)>, usize)> {
+        let longest = self.words.iter().map(|w| w.len()).max().unwrap_or(0) as i32;
+        let mut windows = Vec::new();
+        for (r, row) in grid.iter().enumerate() {
+            for c in 0..row.len() {
+                let window = (0..longest)
+                    .map_while(|i| {
+                        edges.cell(grid, r as i32 + direction.0 * i, c as i32 + direction.1 * i)
+                    })
+                    .collect();
+                windows.push((window, 1));
+            }
+        }
+        windows
+    }
+}
+
+// every maximal straight line through the grid heading in direction
+fn lines(grid: &[Vec<char>], direction: (i32, i32)) -> Vec<(Vec<(usize, usize)>, usize)> {
+    let in_bounds = |r: i32, c: i32| EdgePolicy::Bounded.cell(grid, r, c).is_some();
+
+    let mut lines = Vec::new();
+    for (r, row) in grid.iter().enumerate() {
+        for c in 0..row.len() {
+            // only start where stepping backwards would fall off
+            if in_bounds(r as i32 - direction.0, c as i32 - direction.1) {
+                continue;
+            }
+            let mut line = Vec::new();
+            let (mut cur_r, mut cur_c) = (r as i32, c as i32);
+            while in_bounds(cur_r, cur_c) {
+                line.push((cur_r as usize, cur_c as usize));
+                cur_r += direction.0;
+                cur_c += direction.1;
+            }
+            let starts = line.len();
+            lines.push((line, starts));
+        }
+    }
+    lines
+}
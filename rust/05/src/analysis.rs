@@ -0,0 +1,203 @@
+use crate::graph::{self, CycleError};
+use crate::{Page, RuleMap, Update};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Debug)]
+pub struct RuleReport {
+    // both a|b and b|a, with a < b
+    pub contradictions: Vec<(Page, Page)>,
+    // one example cycle for each group of pages that can all reach each other
+    pub cycles: Vec<CycleError>,
+    pub unknown_pages: Vec<Page>,
+    pub update_orders: Vec<UpdateOrder>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UpdateOrder {
+    Total,
+    // the rules allow more than one order. these neighbours in the sorted order have no rule between them.
+    Ambiguous(Vec<(Page, Page)>),
+    Cyclic(CycleError),
+}
+
+pub fn analyze(rule_map: &RuleMap, updates: &[Update]) -> RuleReport {
+    let mut contradictions: Vec<(Page, Page)> = rule_map
+        .iter()
+        .flat_map(|(&a, after)| after.iter().map(move |&b| (a, b)))
+        .filter(|&(a, b)| a < b && follows(rule_map, a, b))
+        .collect();
+    contradictions.sort();
+
+    let mut cycles: Vec<CycleError> = strongly_connected(rule_map)
+        .into_iter()
+        .filter(|scc| scc.len() > 1 || scc.iter().any(|p| follows(rule_map, *p, *p)))
+        .map(|scc| graph::find_cycle(rule_map, &scc))
+        .collect();
+    cycles.sort_by_key(|c| c.pages[0]);
+
+    let ruled: HashSet<Page> = rule_map
+        .iter()
+        .flat_map(|(&a, after)| after.iter().copied().chain([a]))
+        .collect();
+    let unknown_pages: BTreeSet<Page> = updates
+        .iter()
+        .flatten()
+        .filter(|p| !ruled.contains(p))
+        .copied()
+        .collect();
+
+    RuleReport {
+        contradictions,
+        cycles,
+        unknown_pages: unknown_pages.into_iter().collect(),
+        update_orders: updates.iter().map(|u| update_order(u, rule_map)).collect(),
+    }
+}
+
+// is there a rule b|a
+fn follows(rule_map: &RuleMap, a: Page, b: Page) -> bool {
+    rule_map.get(&b).is_some_and(|after| after.contains(&a))
+}
+
+// the order is only forced if every neighbour in the sorted order has a rule between them
+pub fn update_order(update: &[Page], rule_map: &RuleMap) -> UpdateOrder {
+    match graph::topo_sort(update, rule_map) {
+        Err(cycle) => UpdateOrder::Cyclic(cycle),
+        Ok(sorted) => {
+            let unordered: Vec<(Page, Page)> = sorted
+                .windows(2)
+                .filter(|pair| !follows(rule_map, pair[1], pair[0]))
+                .map(|pair| (pair[0], pair[1]))
+                .collect();
+            if unordered.is_empty() {
+                UpdateOrder::Total
+            } else {
+                UpdateOrder::Ambiguous(unordered)
+            }
+        }
+    }
+}
+
+// Tarjan's algorithm
+fn strongly_connected(rule_map: &RuleMap) -> Vec<HashSet<Page>> {
+    struct Tarjan<'a> {
+        rule_map: &'a RuleMap,
+        index: HashMap<Page, usize>,
+        low: HashMap<Page, usize>,
+        stack: Vec<Page>,
+        on_stack: HashSet<Page>,
+        components: Vec<HashSet<Page>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, page: Page) {
+            let i = self.index.len();
+            self.index.insert(page, i);
+            self.low.insert(page, i);
+            self.stack.push(page);
+            self.on_stack.insert(page);
+
+            let after: Vec<Page> = self
+                .rule_map
+                .get(&page)
+                .map(|a| a.iter().copied().collect())
+                .unwrap_or_default();
+            for next in after {
+                if !self.index.contains_key(&next) {
+                    self.visit(next);
+                    self.low.insert(page, self.low[&page].min(self.low[&next]));
+                } else if self.on_stack.contains(&next) {
+                    self.low
+                        .insert(page, self.low[&page].min(self.index[&next]));
+                }
+            }
+
+            if self.low[&page] == self.index[&page] {
+                let mut component = HashSet::new();
+                loop {
+                    let p = self.stack.pop().unwrap();
+                    self.on_stack.remove(&p);
+                    component.insert(p);
+                    if p == page {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        rule_map,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    let mut pages: Vec<Page> = rule_map.keys().copied().collect();
+    pages.sort();
+    for page in pages {
+        if !tarjan.index.contains_key(&page) {
+            tarjan.visit(page);
+        }
+    }
+    tarjan.components
+}
+
+pub fn format_report(report: &RuleReport, updates: &[Update]) -> String {
+    let mut out = String::new();
+
+    out += &format!("contradictory pairs: {}\n", report.contradictions.len());
+    for (a, b) in &report.contradictions {
+        out += &format!("  {}|{} and {}|{}\n", a, b, b, a);
+    }
+
+    out += &format!("cycles: {}\n", report.cycles.len());
+    for cycle in &report.cycles {
+        out += &format!("  {}\n", cycle);
+    }
+
+    let unknown: Vec<String> = report.unknown_pages.iter().map(|p| p.to_string()).collect();
+    out += &format!("pages without rules: {}\n", unknown.join(", "));
+
+    let count = |f: fn(&UpdateOrder) -> bool| report.update_orders.iter().filter(|o| f(o)).count();
+    out += &format!(
+        "updates with a total order: {} of {}\n",
+        count(|o| *o == UpdateOrder::Total),
+        updates.len()
+    );
+    out += &format!(
+        "ambiguous updates: {}\n",
+        count(|o| matches!(o, UpdateOrder::Ambiguous(_)))
+    );
+    out += &format!(
+        "cyclic updates: {}\n",
+        count(|o| matches!(o, UpdateOrder::Cyclic(_)))
+    );
+
+    let smallest = report
+        .update_orders
+        .iter()
+        .enumerate()
+        .filter_map(|(i, o)| match o {
+            UpdateOrder::Ambiguous(pairs) => Some((i, pairs)),
+            _ => None,
+        })
+        .min_by_key(|(i, _)| updates[*i].len());
+    if let Some((i, pairs)) = smallest {
+        let pages: Vec<String> = updates[i].iter().map(|p| p.to_string()).collect();
+        let pairs: Vec<String> = pairs
+            .iter()
+            .map(|(a, b)| format!("{} / {}", a, b))
+            .collect();
+        out += &format!(
+            "smallest ambiguous update: #{}: {}\n  no rule between: {}\n",
+            i + 1,
+            pages.join(","),
+            pairs.join(", ")
+        );
+    }
+
+    out
+}
@@ -0,0 +1,121 @@
+use crate::graph::{self, CycleError};
+use crate::{Page, RuleMap};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions<'a> {
+    pub format: Format,
+    // only draw the pages in this update, with the rules it breaks in red
+    pub update: Option<&'a [Page]>,
+    // drop every rule that's implied by other rules. only makes sense without cycles.
+    pub reduce: bool,
+}
+
+pub fn export(rule_map: &RuleMap, options: &ExportOptions) -> Result<String, CycleError> {
+    let graph = match options.update {
+        Some(update) => graph::induced_subgraph(update, rule_map),
+        None => rule_map.clone(),
+    };
+    let mut edges: BTreeSet<(Page, Page)> = graph
+        .iter()
+        .flat_map(|(&a, after)| after.iter().map(move |&b| (a, b)))
+        .collect();
+    let mut pages: BTreeSet<Page> = graph.keys().copied().collect();
+    pages.extend(edges.iter().map(|&(_, b)| b));
+
+    if options.reduce {
+        let all: Vec<Page> = pages.iter().copied().collect();
+        graph::topo_sort(&all, &graph)?;
+        edges = transitive_reduction(&graph, &edges);
+    }
+
+    // if a rule that got reduced away was broken, one of the rules implying it is broken too,
+    // so there's still something red to look at.
+    let positions: HashMap<Page, usize> = options
+        .update
+        .map(|u| u.iter().enumerate().map(|(i, &p)| (p, i)).collect())
+        .unwrap_or_default();
+    let violated = |&(a, b): &(Page, Page)| match (positions.get(&a), positions.get(&b)) {
+        (Some(i), Some(j)) => j < i,
+        _ => false,
+    };
+
+    Ok(match options.format {
+        Format::Dot => dot(&pages, &edges, violated),
+        Format::Mermaid => mermaid(&pages, &edges, violated),
+    })
+}
+
+// a -> b can go if b is still reachable from a some other way
+fn transitive_reduction(graph: &RuleMap, edges: &BTreeSet<(Page, Page)>) -> BTreeSet<(Page, Page)> {
+    edges
+        .iter()
+        .filter(|&&(a, b)| {
+            !graph[&a]
+                .iter()
+                .filter(|&&c| c != b)
+                .any(|&c| reachable(graph, c, b))
+        })
+        .copied()
+        .collect()
+}
+
+fn reachable(graph: &RuleMap, from: Page, to: Page) -> bool {
+    let mut seen: HashSet<Page> = HashSet::new();
+    let mut stack = vec![from];
+    while let Some(page) = stack.pop() {
+        if page == to {
+            return true;
+        }
+        if seen.insert(page) {
+            if let Some(after) = graph.get(&page) {
+                stack.extend(after);
+            }
+        }
+    }
+    false
+}
+
+fn dot(
+    pages: &BTreeSet<Page>,
+    edges: &BTreeSet<(Page, Page)>,
+    violated: impl Fn(&(Page, Page)) -> bool,
+) -> String {
+    let mut out = String::from("digraph rules {\n");
+    for page in pages {
+        out += &format!("  {};\n", page);
+    }
+    for edge in edges {
+        let style = if violated(edge) { " [color=red]" } else { "" };
+        out += &format!("  {} -> {}{};\n", edge.0, edge.1, style);
+    }
+    out + "}\n"
+}
+
+fn mermaid(
+    pages: &BTreeSet<Page>,
+    edges: &BTreeSet<(Page, Page)>,
+    violated: impl Fn(&(Page, Page)) -> bool,
+) -> String {
+    let mut out = String::from("graph TD\n");
+    for page in pages {
+        out += &format!("  p{}[{}]\n", page, page);
+    }
+    let mut red = Vec::new();
+    for (i, edge) in edges.iter().enumerate() {
+        out += &format!("  p{} --> p{}\n", edge.0, edge.1);
+        if violated(edge) {
+            red.push(i.to_string());
+        }
+    }
+    if !red.is_empty() {
+        out += &format!("  linkStyle {} stroke:red\n", red.join(","));
+    }
+    out
+}
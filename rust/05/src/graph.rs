@@ -0,0 +1,91 @@
+use crate::{Page, RuleMap, Update};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct CycleError {
+    // in order, each page has to come before the next, and the last before the first
+    pub pages: Vec<Page>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pages: Vec<String> = self.pages.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "rules contain a cycle: {} -> {}",
+            pages.join(" -> "),
+            pages[0]
+        )
+    }
+}
+
+// just the rules between pages that are in the update
+pub fn induced_subgraph(update: &[Page], rule_map: &RuleMap) -> RuleMap {
+    let pages: HashSet<Page> = update.iter().copied().collect();
+    update
+        .iter()
+        .map(|page| {
+            let after: HashSet<Page> = rule_map
+                .get(page)
+                .map(|rules| rules.intersection(&pages).copied().collect())
+                .unwrap_or_default();
+            (*page, after)
+        })
+        .collect()
+}
+
+// Kahn's algorithm. when more than one page is free to go next, the one earliest in the update wins,
+// so a valid update comes back unchanged.
+pub fn topo_sort(update: &[Page], rule_map: &RuleMap) -> Result<Update, CycleError> {
+    let graph = induced_subgraph(update, rule_map);
+    let mut in_degree: HashMap<Page, usize> = update.iter().map(|&p| (p, 0)).collect();
+    for after in graph.values() {
+        for page in after {
+            *in_degree.get_mut(page).unwrap() += 1;
+        }
+    }
+
+    let mut sorted = Vec::with_capacity(update.len());
+    while let Some(&next) = update.iter().find(|p| in_degree.get(p) == Some(&0)) {
+        in_degree.remove(&next);
+        for page in &graph[&next] {
+            *in_degree.get_mut(page).unwrap() -= 1;
+        }
+        sorted.push(next);
+    }
+
+    if in_degree.is_empty() {
+        Ok(sorted)
+    } else {
+        let stuck: HashSet<Page> = in_degree.keys().copied().collect();
+        Err(find_cycle(&graph, &stuck))
+    }
+}
+
+// every page left over still has something before it that's also left over, so walking backwards
+// through those has to loop eventually.
+pub fn find_cycle(graph: &RuleMap, stuck: &HashSet<Page>) -> CycleError {
+    let before = |page: Page| {
+        *graph
+            .iter()
+            .filter(|(p, after)| stuck.contains(p) && after.contains(&page))
+            .map(|(p, _)| p)
+            .min()
+            .unwrap()
+    };
+
+    let mut path: Vec<Page> = vec![*stuck.iter().min().unwrap()];
+    loop {
+        let prev = before(*path.last().unwrap());
+        if let Some(start) = path.iter().position(|&p| p == prev) {
+            let mut pages = path[start..].to_vec();
+            pages.reverse();
+            // start from the smallest page so the same cycle always reads the same way
+            let smallest = (0..pages.len()).min_by_key(|&i| pages[i]).unwrap();
+            pages.rotate_left(smallest);
+            return CycleError { pages };
+        }
+        path.push(prev);
+    }
+}
@@ -1,5 +1,13 @@
+mod analysis;
+mod export;
+mod graph;
+mod repair;
+mod validator;
+
+use graph::CycleError;
 use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::io::{self, BufRead};
+use std::{env, fs};
 
 type Page = u32;
 type Update = Vec<Page>;
@@ -14,8 +22,119 @@ struct Input {
 fn main() {
     let input = parse_input(false);
 
+    // cargo run -- export dot | mermaid [--update=N] [--reduce]
+    // N counts updates from 1
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "export") {
+        let format = match select_format(args.get(1).map(|a| a.as_str())) {
+            Ok(format) => format,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        let update = match args.iter().find_map(|a| a.strip_prefix("--update=")) {
+            Some(n) => match select_update(n, &input.updates) {
+                Ok(update) => Some(update),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            },
+            None => None,
+        };
+        let options = export::ExportOptions {
+            format,
+            update,
+            reduce: args.iter().any(|a| a == "--reduce"),
+        };
+        match export::export(&input.rules_per_page, &options) {
+            Ok(out) => print!("{}", out),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // cargo run -- validate [--verbose] < updates.txt
+    // checks each line against the rules in input.txt as it comes in
+    if args.first().is_some_and(|a| a == "validate") {
+        let validator = validator::Validator::compile(&input.rules_per_page);
+        if args.iter().any(|a| a == "--verbose") {
+            eprintln!("using {:?} validation", validator.strategy());
+        }
+        for line in io::stdin().lock().lines() {
+            let line = line.unwrap();
+            if line.trim().is_empty() {
+                continue;
+            }
+            let update: Update = match line.trim().split(",").map(|s| s.trim().parse()).collect() {
+                Ok(update) => update,
+                Err(e) => {
+                    // one bad line shouldn't stop the stream
+                    println!("{}: can't read it ({})", line, e);
+                    continue;
+                }
+            };
+            match validator.first_violation(&update) {
+                None => println!("{}: valid", line),
+                Some(i) => println!("{}: invalid at page {} (index {})", line, update[i], i),
+            }
+        }
+        return;
+    }
+
+    // cargo run -- repair
+    if args.first().is_some_and(|a| a == "repair") {
+        for (i, update) in input.updates.iter().enumerate() {
+            if is_valid(update, &input.rules_per_page) {
+                continue;
+            }
+            let (_, fix_moves) = fix_counting_moves(update, &input.rules_per_page);
+            match repair::min_moves(update, &input.rules_per_page) {
+                Ok(r) => print!("{}", repair::format_repair(i + 1, &r, fix_moves)),
+                Err(e) => println!("update #{}: {}", i + 1, e),
+            }
+        }
+        return;
+    }
+
+    // cargo run -- analyze
+    if args.first().is_some_and(|a| a == "analyze") {
+        let report = analysis::analyze(&input.rules_per_page, &input.updates);
+        print!("{}", analysis::format_report(&report, &input.updates));
+        return;
+    }
+
     println!("part 1: {}", part1(&input.updates, &input.rules_per_page));
     println!("part 2: {}", part2(&input.updates, &input.rules_per_page));
+    match part2_topo(&input.updates, &input.rules_per_page) {
+        Ok(answer) => println!("part 2 (topological): {}", answer),
+        Err(e) => println!("part 2 (topological): {}", e),
+    }
+}
+
+// dot unless it says otherwise, and a flag straight after export isn't a format
+fn select_format(arg: Option<&str>) -> Result<export::Format, String> {
+    match arg {
+        Some("dot") => Ok(export::Format::Dot),
+        Some("mermaid") => Ok(export::Format::Mermaid),
+        Some(a) if !a.starts_with("--") => Err(format!("export wants dot or mermaid, got {:?}", a)),
+        _ => Ok(export::Format::Dot),
+    }
+}
+
+// --update=N, counting from 1
+fn select_update<'a>(n: &str, updates: &'a [Update]) -> Result<&'a [Page], String> {
+    n.parse::<usize>()
+        .ok()
+        .filter(|&n| n >= 1)
+        .and_then(|n| updates.get(n - 1))
+        .map(|u| u.as_slice())
+        .ok_or(format!(
+            "--update wants a number from 1 to {}, got {:?}",
+            updates.len(),
+            n
+        ))
 }
 
 fn parse_input(toy: bool) -> Input {
@@ -53,10 +172,11 @@ fn parse_updates(update_str: &str) -> Vec<Update> {
         .collect()
 }
 
-fn part1(updates: &Vec<Update>, rule_map: &RuleMap) -> u32 {
+fn part1(updates: &[Update], rule_map: &RuleMap) -> u32 {
+    let validator = validator::Validator::compile(rule_map);
     updates
         .iter()
-        .filter(|update| is_valid(update, rule_map))
+        .filter(|update| validator.is_valid(update))
         .map(middle_elem)
         .sum()
 }
@@ -79,7 +199,7 @@ fn middle_elem(update: &Update) -> u32 {
     update[update.len() / 2]
 }
 
-fn part2(updates: &Vec<Update>, rule_map: &RuleMap) -> u32 {
+fn part2(updates: &[Update], rule_map: &RuleMap) -> u32 {
     updates
         .iter()
         .filter(|update| !is_valid(update, rule_map))
@@ -87,13 +207,26 @@ fn part2(updates: &Vec<Update>, rule_map: &RuleMap) -> u32 {
         .sum()
 }
 
+fn part2_topo(updates: &[Update], rule_map: &RuleMap) -> Result<u32, CycleError> {
+    updates
+        .iter()
+        .filter(|update| !is_valid(update, rule_map))
+        .map(|update| graph::topo_sort(update, rule_map).map(|fixed| middle_elem(&fixed)))
+        .sum()
+}
+
 fn fix(update: &Update, rule_map: &RuleMap) -> Update {
+    fix_counting_moves(update, rule_map).0
+}
+
+fn fix_counting_moves(update: &Update, rule_map: &RuleMap) -> (Update, usize) {
     let mut fixed = update.clone();
+    let mut moves = 0;
 
     let mut i = 0;
     while i < fixed.len() {
         let cur = fixed[i];
-        
+
         // search to end of list for pages that this page *should* be after.
         // we'll move it to after the last one.
         let maybe_new_index = (i + 1..fixed.len())
@@ -103,13 +236,14 @@ fn fix(update: &Update, rule_map: &RuleMap) -> Update {
         if let Some(new_index) = maybe_new_index {
             fixed.remove(i);
             fixed.insert(new_index, cur);
+            moves += 1;
         } else {
             // we don't have a new element at the current index, so we can move on
             i += 1;
         }
     }
 
-    fixed
+    (fixed, moves)
 }
 
 fn cur_should_follow(cur: Page, other: Page, rule_map: &RuleMap) -> bool {
@@ -136,4 +270,252 @@ mod tests {
         let result = part2(&input.updates, &input.rules_per_page);
         assert_eq!(result, 4655);
     }
+
+    #[test]
+    fn part2_topo_answer() {
+        let input = parse_input(false);
+        let result = part2_topo(&input.updates, &input.rules_per_page);
+        assert_eq!(result, Ok(4655));
+    }
+
+    #[test]
+    fn toy_answers() {
+        let input = parse_input(true);
+        assert_eq!(part1(&input.updates, &input.rules_per_page), 143);
+        assert_eq!(part2(&input.updates, &input.rules_per_page), 123);
+        assert_eq!(part2_topo(&input.updates, &input.rules_per_page), Ok(123));
+    }
+
+    #[test]
+    fn topo_sort_matches_fix() {
+        let input = parse_input(true);
+        for update in &input.updates {
+            let sorted = graph::topo_sort(update, &input.rules_per_page).unwrap();
+            assert!(is_valid(&sorted, &input.rules_per_page));
+            assert_eq!(sorted, fix(update, &input.rules_per_page));
+        }
+    }
+
+    #[test]
+    fn rule_analysis() {
+        let rules = parse_rules("1|2\n2|1\n2|3\n3|4\n4|2\n5|6\n5|7");
+        let updates = parse_updates("5,6,7\n1,2\n5,6\n3,4,8\n5,6,7,9");
+        let report = analysis::analyze(&rules, &updates);
+
+        assert_eq!(report.contradictions, [(1, 2)]);
+        // 1, 2, 3, 4 can all reach each other
+        assert_eq!(report.cycles.len(), 1);
+        assert_eq!(report.unknown_pages, [8, 9]);
+        assert_eq!(
+            report.update_orders,
+            [
+                analysis::UpdateOrder::Ambiguous(vec![(6, 7)]),
+                analysis::UpdateOrder::Cyclic(CycleError { pages: vec![1, 2] }),
+                analysis::UpdateOrder::Total,
+                analysis::UpdateOrder::Ambiguous(vec![(4, 8)]),
+                analysis::UpdateOrder::Ambiguous(vec![(6, 7), (7, 9)]),
+            ]
+        );
+
+        let text = analysis::format_report(&report, &updates);
+        assert!(text.contains("smallest ambiguous update: #1: 5,6,7\n"));
+    }
+
+    #[test]
+    fn toy_rules_are_total_on_every_update() {
+        let input = parse_input(true);
+        let report = analysis::analyze(&input.rules_per_page, &input.updates);
+        assert!(report.contradictions.is_empty());
+        assert!(report.cycles.is_empty());
+        assert!(report
+            .update_orders
+            .iter()
+            .all(|o| *o == analysis::UpdateOrder::Total));
+    }
+
+    #[test]
+    fn export_update_graph() {
+        let rules = parse_rules("1|2\n2|3\n1|3\n3|4");
+        let update = [1, 3, 2];
+        let options = export::ExportOptions {
+            format: export::Format::Dot,
+            update: Some(&update),
+            reduce: false,
+        };
+        assert_eq!(
+            export::export(&rules, &options).unwrap(),
+            "digraph rules {\n  1;\n  2;\n  3;\n  1 -> 2;\n  1 -> 3;\n  2 -> 3 [color=red];\n}\n"
+        );
+
+        let options = export::ExportOptions {
+            format: export::Format::Mermaid,
+            reduce: true,
+            ..options
+        };
+        assert_eq!(
+            export::export(&rules, &options).unwrap(),
+            "graph TD\n  p1[1]\n  p2[2]\n  p3[3]\n  p1 --> p2\n  p2 --> p3\n  linkStyle 1 stroke:red\n"
+        );
+    }
+
+    #[test]
+    fn export_reduction_needs_a_dag() {
+        let rules = parse_rules("1|2\n2|1");
+        let options = export::ExportOptions {
+            format: export::Format::Dot,
+            update: None,
+            reduce: true,
+        };
+        assert!(export::export(&rules, &options).is_err());
+        let options = export::ExportOptions {
+            reduce: false,
+            ..options
+        };
+        assert!(export::export(&rules, &options).is_ok());
+    }
+
+    #[test]
+    fn min_moves_on_toy_input() {
+        let input = parse_input(true);
+        let invalid: Vec<&Update> = input
+            .updates
+            .iter()
+            .filter(|u| !is_valid(u, &input.rules_per_page))
+            .collect();
+        let summary: Vec<(usize, usize)> = invalid
+            .iter()
+            .map(|u| {
+                let repair = repair::min_moves(u, &input.rules_per_page).unwrap();
+                assert!(repair.exact);
+                assert_eq!(repair.fixed, fix(u, &input.rules_per_page));
+                (
+                    repair.moves.len(),
+                    fix_counting_moves(u, &input.rules_per_page).1,
+                )
+            })
+            .collect();
+        assert_eq!(summary, [(1, 1), (1, 1), (2, 2)]);
+
+        // 97,13,75,29,47 -> 97,75,47,29,13
+        let repair = repair::min_moves(invalid[2], &input.rules_per_page).unwrap();
+        assert_eq!(
+            repair.moves,
+            [
+                repair::Move {
+                    page: 29,
+                    from: 3,
+                    to: 4
+                },
+                repair::Move {
+                    page: 13,
+                    from: 1,
+                    to: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn min_moves_beats_fix() {
+        let rules = parse_rules("1|2\n2|3\n3|4");
+        let update = vec![2, 3, 4, 1];
+        let repair = repair::min_moves(&update, &rules).unwrap();
+        assert_eq!(
+            repair.moves,
+            [repair::Move {
+                page: 1,
+                from: 3,
+                to: 0
+            }]
+        );
+        assert_eq!(fix_counting_moves(&update, &rules), (vec![1, 2, 3, 4], 3));
+    }
+
+    #[test]
+    fn validator_agrees_with_is_valid() {
+        let input = parse_input(true);
+        let validator = validator::Validator::compile(&input.rules_per_page);
+        // every pair of toy pages has a rule
+        assert_eq!(validator.strategy(), validator::Strategy::Rank);
+        for update in &input.updates {
+            assert_eq!(
+                validator.is_valid(update),
+                is_valid(update, &input.rules_per_page)
+            );
+        }
+
+        // knock out one rule and it has to fall back to the bitsets
+        let mut rules = input.rules_per_page.clone();
+        rules.get_mut(&47).unwrap().remove(&53);
+        let validator = validator::Validator::compile(&rules);
+        assert_eq!(validator.strategy(), validator::Strategy::Bitset);
+        for update in input
+            .updates
+            .iter()
+            .chain([&vec![53, 47], &vec![61, 53, 47]])
+        {
+            assert_eq!(validator.is_valid(update), is_valid(update, &rules));
+        }
+        assert_eq!(validator.first_violation(&[61, 53, 47, 99]), Some(2));
+    }
+
+    #[test]
+    fn validator_with_huge_page_numbers() {
+        // the index is keyed by page, so a big page number doesn't mean a big allocation
+        let rules = parse_rules("4000000000|7\n7|4294967295");
+        let validator = validator::Validator::compile(&rules);
+        assert!(validator.is_valid(&[4000000000, 7, 4294967295]));
+        assert_eq!(validator.first_violation(&[7, 4000000000]), Some(1));
+        assert!(validator.is_valid(&[123456789, 7]));
+    }
+
+    #[test]
+    fn topo_sort_reports_cycle() {
+        let rules = parse_rules("1|2\n2|3\n3|1\n3|4");
+        let result = graph::topo_sort(&[4, 3, 2, 1], &rules);
+        assert_eq!(
+            result,
+            Err(CycleError {
+                pages: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "rules contain a cycle: 1 -> 2 -> 3 -> 1"
+        );
+
+        // the cycle doesn't matter if the update doesn't have all of it
+        assert_eq!(graph::topo_sort(&[4, 3, 1], &rules), Ok(vec![3, 4, 1]));
+    }
+
+    #[test]
+    fn select_format_names() {
+        assert_eq!(select_format(Some("mermaid")), Ok(export::Format::Mermaid));
+        assert_eq!(select_format(Some("dot")), Ok(export::Format::Dot));
+        assert_eq!(select_format(None), Ok(export::Format::Dot));
+        assert_eq!(select_format(Some("--reduce")), Ok(export::Format::Dot));
+        assert_eq!(
+            select_format(Some("mermiad")),
+            Err("export wants dot or mermaid, got \"mermiad\"".to_string())
+        );
+    }
+
+    #[test]
+    fn select_update_bounds() {
+        let input = parse_input(true);
+        assert_eq!(
+            select_update("1", &input.updates),
+            Ok(&[75, 47, 61, 53, 29][..])
+        );
+        assert!(select_update("6", &input.updates).is_ok());
+        for bad in ["0", "7", "-1", "two", ""] {
+            assert_eq!(
+                select_update(bad, &input.updates),
+                Err(format!(
+                    "--update wants a number from 1 to 6, got {:?}",
+                    bad
+                ))
+            );
+        }
+    }
 }
@@ -0,0 +1,102 @@
+use crate::graph::{self, CycleError};
+use crate::{Page, RuleMap, Update};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    pub page: Page,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub moves: Vec<Move>,
+    pub fixed: Update,
+    // false if the rules allow more than one order, in which case we only aimed for the order
+    // topo_sort picks and the move count is an upper bound
+    pub exact: bool,
+}
+
+// each move takes one page out and puts it back somewhere else. the pages that don't move keep their
+// relative order, so the fewest moves comes from keeping the longest run that's already in order.
+pub fn min_moves(update: &[Page], rule_map: &RuleMap) -> Result<Repair, CycleError> {
+    let target = graph::topo_sort(update, rule_map)?;
+    let rank: HashMap<Page, usize> = target.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+    let kept = longest_increasing(&update.iter().map(|p| rank[p]).collect::<Vec<usize>>());
+    let mut placed: HashSet<Page> = kept.iter().map(|&i| update[i]).collect();
+
+    // drop each moved page in right after whatever comes before it in the target and is already settled
+    let mut current = update.to_vec();
+    let mut moves = Vec::new();
+    for (t, &page) in target.iter().enumerate() {
+        if placed.contains(&page) {
+            continue;
+        }
+        let from = current.iter().position(|&p| p == page).unwrap();
+        current.remove(from);
+        let to = target[..t]
+            .iter()
+            .rev()
+            .find(|p| placed.contains(p))
+            .map_or(0, |prev| {
+                current.iter().position(|p| p == prev).unwrap() + 1
+            });
+        current.insert(to, page);
+        placed.insert(page);
+        moves.push(Move { page, from, to });
+    }
+
+    let exact = target.windows(2).all(|pair| {
+        rule_map
+            .get(&pair[0])
+            .is_some_and(|after| after.contains(&pair[1]))
+    });
+    Ok(Repair {
+        moves,
+        fixed: current,
+        exact,
+    })
+}
+
+// indices of one longest strictly increasing subsequence
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    let mut longest: Vec<usize> = vec![1; values.len()];
+    let mut prev: Vec<Option<usize>> = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && longest[j] + 1 > longest[i] {
+                longest[i] = longest[j] + 1;
+                prev[i] = Some(j);
+            }
+        }
+    }
+
+    let mut indices = Vec::new();
+    let mut cur = (0..values.len()).max_by_key(|&i| longest[i]);
+    while let Some(i) = cur {
+        indices.push(i);
+        cur = prev[i];
+    }
+    indices.reverse();
+    indices
+}
+
+pub fn format_repair(update_number: usize, repair: &Repair, fix_moves: usize) -> String {
+    let pages: Vec<String> = repair.fixed.iter().map(|p| p.to_string()).collect();
+    let mut out = format!(
+        "update #{}: {}{} moves (fix made {}) -> {}\n",
+        update_number,
+        if repair.exact { "" } else { "at most " },
+        repair.moves.len(),
+        fix_moves,
+        pages.join(",")
+    );
+    for m in &repair.moves {
+        out += &format!(
+            "  page {} moved from index {} to index {}\n",
+            m.page, m.from, m.to
+        );
+    }
+    out
+}
@@ -0,0 +1,115 @@
+use crate::graph;
+use crate::{Page, RuleMap};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    // every pair of pages has a rule, so the rules are one big ordering and each page just gets a rank
+    Rank,
+    // otherwise, a bitset per page of everything that has to come after it
+    Bitset,
+}
+
+// built once from the rules, then checks any number of updates with one HashMap lookup per page
+// and no HashSets
+#[derive(Debug)]
+pub struct Validator {
+    // page number -> dense index. pages no rule mentions aren't in it.
+    index: HashMap<Page, usize>,
+    ranks: Option<Vec<usize>>,
+    must_follow: Vec<Vec<u64>>,
+}
+
+impl Validator {
+    pub fn compile(rule_map: &RuleMap) -> Validator {
+        let mut pages: Vec<Page> = rule_map
+            .iter()
+            .flat_map(|(&a, after)| after.iter().copied().chain([a]))
+            .collect();
+        pages.sort();
+        pages.dedup();
+
+        let index: HashMap<Page, usize> = pages.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+
+        let words = pages.len().div_ceil(64);
+        let mut must_follow: Vec<Vec<u64>> = vec![vec![0; words]; pages.len()];
+        let mut rule_count = 0;
+        for (&a, after) in rule_map {
+            for &b in after {
+                let j = index[&b];
+                must_follow[index[&a]][j / 64] |= 1 << (j % 64);
+                rule_count += 1;
+            }
+        }
+
+        // n pages with a rule for every pair and no cycles is a total order,
+        // and sorting them gives the ranks
+        let pairs = pages.len() * pages.len().saturating_sub(1) / 2;
+        let ranks = (rule_count == pairs)
+            .then(|| graph::topo_sort(&pages, rule_map).ok())
+            .flatten()
+            .map(|sorted| {
+                let mut ranks = vec![0; pages.len()];
+                for (rank, page) in sorted.iter().enumerate() {
+                    ranks[index[page]] = rank;
+                }
+                ranks
+            });
+
+        Validator {
+            index,
+            ranks,
+            must_follow,
+        }
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        if self.ranks.is_some() {
+            Strategy::Rank
+        } else {
+            Strategy::Bitset
+        }
+    }
+
+    fn dense(&self, page: Page) -> Option<usize> {
+        self.index.get(&page).copied()
+    }
+
+    // index of the first page that had to come before something already seen
+    pub fn first_violation(&self, update: &[Page]) -> Option<usize> {
+        match &self.ranks {
+            Some(ranks) => {
+                let mut highest: Option<usize> = None;
+                for (i, &page) in update.iter().enumerate() {
+                    if let Some(rank) = self.dense(page).map(|d| ranks[d]) {
+                        if highest.is_some_and(|h| rank < h) {
+                            return Some(i);
+                        }
+                        highest = Some(rank);
+                    }
+                }
+                None
+            }
+            None => {
+                let mut seen: Vec<u64> = vec![0; self.must_follow.first().map_or(0, |w| w.len())];
+                for (i, &page) in update.iter().enumerate() {
+                    if let Some(d) = self.dense(page) {
+                        if seen
+                            .iter()
+                            .zip(&self.must_follow[d])
+                            .any(|(s, f)| s & f != 0)
+                        {
+                            return Some(i);
+                        }
+                        seen[d / 64] |= 1 << (d % 64);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    pub fn is_valid(&self, update: &[Page]) -> bool {
+        self.first_violation(update).is_none()
+    }
+}
@@ -0,0 +1,36 @@
+// a number of slots, one bit each. stands in for a HashSet when everything has a dense index.
+#[derive(Clone, Debug)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> BitSet {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    // false if it was already there, same as HashSet::insert
+    pub fn insert(&mut self, i: usize) -> bool {
+        let was_there = self.contains(i);
+        self.words[i / 64] |= 1 << (i % 64);
+        !was_there
+    }
+
+    // room for at least len slots, any new ones empty
+    pub fn grow(&mut self, len: usize) {
+        let words = len.div_ceil(64);
+        if self.words.len() < words {
+            self.words.resize(words, 0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::bitset::BitSet;
+use crate::{Guard, Map, Pos};
+
+// directions in the order the guard turns through them
+const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+// for every cell and direction, the x (heading left/right) or y (heading up/down) of the first
+// obstacle the guard would hit, or None if it walks off the map.
+#[derive(Debug)]
+pub struct JumpTable {
+    width: usize,
+    next_obstacle: [Vec<Vec<Option<usize>>>; 4],
+}
+
+impl JumpTable {
+    pub fn new(map: &Map) -> JumpTable {
+        let width = map.max_x + 1;
+        let height = map.max_y + 1;
+        let mut next_obstacle: [Vec<Vec<Option<usize>>>; 4] =
+            std::array::from_fn(|_| vec![vec![None; width]; height]);
+
+        // fill in from the far side, so the cell ahead is always done first
+        for (d, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+            let xs: Vec<usize> = if dx > 0 {
+                (0..width).rev().collect()
+            } else {
+                (0..width).collect()
+            };
+            let ys: Vec<usize> = if dy > 0 {
+                (0..height).rev().collect()
+            } else {
+                (0..height).collect()
+            };
+            for &y in &ys {
+                for &x in &xs {
+                    let ahead = Pos { x, y }
+                        .add(dx, dy)
+                        .filter(|a| a.x <= map.max_x && a.y <= map.max_y);
+                    next_obstacle[d][y][x] = match ahead {
+                        None => None,
+                        Some(a) if map.is_obstacle(a) => Some(if dx == 0 { a.y } else { a.x }),
+                        Some(a) => next_obstacle[d][a.y][a.x],
+                    };
+                }
+            }
+        }
+
+        JumpTable {
+            width,
+            next_obstacle,
+        }
+    }
+
+    // the table only knows the map's own obstacles, so check if the extra one is closer
+    fn obstacle_ahead(&self, guard: &Guard, extra_obstacle: Option<Pos>) -> Option<Pos> {
+        let d = guard.direction_index();
+        let Pos { x, y } = guard.pos;
+        let ahead = |p: Pos| match d {
+            0 => p.x == x && p.y > y,
+            1 => p.y == y && p.x > x,
+            2 => p.x == x && p.y < y,
+            _ => p.y == y && p.x < x,
+        };
+        let distance = |p: Pos| x.abs_diff(p.x) + y.abs_diff(p.y);
+
+        let from_table = self.next_obstacle[d][y][x].map(|n| match d {
+            0 | 2 => Pos { x, y: n },
+            _ => Pos { x: n, y },
+        });
+        match (from_table, extra_obstacle.filter(|&e| ahead(e))) {
+            (Some(t), Some(e)) => Some(if distance(e) < distance(t) { e } else { t }),
+            (t, e) => t.or(e),
+        }
+    }
+
+    // teleport the guard up to the next obstacle and turn. false if it walks off the map, or it's
+    // boxed in and can't go anywhere (same as move_guard).
+    pub fn jump(&self, guard: &mut Guard, extra_obstacle: Option<Pos>) -> bool {
+        for _ in 0..4 {
+            let Some(obstacle) = self.obstacle_ahead(guard, extra_obstacle) else {
+                return false;
+            };
+            let before = Pos {
+                x: (obstacle.x as i32 - guard.direction.0) as usize,
+                y: (obstacle.y as i32 - guard.direction.1) as usize,
+            };
+            let moved = before != guard.pos;
+            guard.pos = before;
+            guard.rotate();
+            if moved {
+                return true;
+            }
+        }
+        false
+    }
+
+    // turns is a BitSet from Map::guard_states, cleared here so it can be reused
+    pub fn check_cycle(&self, mut guard: Guard, extra_obstacle: Pos, turns: &mut BitSet) -> bool {
+        // only turns get recorded, so there are far fewer states than stepping one cell at a time
+        turns.clear();
+        while self.jump(&mut guard, Some(extra_obstacle)) {
+            // same numbering as Map::state_index
+            let state = (guard.pos.y * self.width + guard.pos.x) * 4 + guard.direction_index();
+            if !turns.insert(state) {
+                return true;
+            }
+        }
+        false
+    }
+}
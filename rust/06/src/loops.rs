@@ -0,0 +1,78 @@
+use crate::jump::JumpTable;
+use crate::{get_guard_positions, Guard, Map, Outcome, Pos};
+use rayon::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopInfo {
+    pub obstacle: Pos,
+    // steps to go round the loop once
+    pub period: usize,
+    // the first step the guard is on the loop for good
+    pub entered_at: usize,
+}
+
+pub fn find_loop(map: &Map, guard: Guard, extra_obstacle: Pos) -> Option<LoopInfo> {
+    match map.patrol(guard, Some(extra_obstacle)).0 {
+        Outcome::Looped { entered_at, period } => Some(LoopInfo {
+            obstacle: extra_obstacle,
+            period,
+            entered_at,
+        }),
+        Outcome::Exited(_) | Outcome::Trapped => None,
+    }
+}
+
+// in reading order, top to bottom then left to right. the jump table weeds out the candidates
+// that don't loop, same as part2, and only the ones left get walked a step at a time.
+pub fn loop_obstacles(map: &Map, guard: Guard) -> Vec<LoopInfo> {
+    let jumps = JumpTable::new(map);
+    let mut loops: Vec<LoopInfo> = get_guard_positions(map, guard)
+        .par_iter()
+        .filter(|&&pos| {
+            pos != guard.pos && map.with_guard_states(|seen| jumps.check_cycle(guard, pos, seen))
+        })
+        .filter_map(|&pos| find_loop(map, guard, pos))
+        .collect();
+    loops.sort_by_key(|l| (map.max_y - l.obstacle.y, l.obstacle.x));
+    loops
+}
+
+pub fn format_loops(map: &Map, loops: &[LoopInfo]) -> String {
+    loops
+        .iter()
+        .map(|l| {
+            format!(
+                "O at row {}, col {}: loop of {} steps, entered at step {}\n",
+                map.max_y - l.obstacle.y,
+                l.obstacle.x,
+                l.period,
+                l.entered_at
+            )
+        })
+        .collect()
+}
+
+// the map as it came in, with an O on each obstruction that makes a loop
+pub fn render(map: &Map, guard: Guard, loops: &[LoopInfo]) -> String {
+    let marks: Vec<Pos> = loops.iter().map(|l| l.obstacle).collect();
+    (0..=map.max_y)
+        .rev()
+        .map(|y| {
+            let row: String = (0..=map.max_x)
+                .map(|x| {
+                    let pos = Pos { x, y };
+                    if pos == guard.pos {
+                        guard.to_char()
+                    } else if map.is_obstacle(pos) {
+                        '#'
+                    } else if marks.contains(&pos) {
+                        'O'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            row + "\n"
+        })
+        .collect()
+}
@@ -1,5 +1,13 @@
+mod bitset;
+mod jump;
+mod loops;
+mod patrol;
+mod replay;
+
+use bitset::BitSet;
+use jump::JumpTable;
 use rayon::prelude::*;
-use std::{collections::HashSet, fs};
+use std::{cell::RefCell, fs, path::Path, time::Duration};
 
 // "up" is +y! flipping the array to start.
 
@@ -43,6 +51,16 @@ impl Guard {
         }
     }
 
+    fn to_char(self) -> char {
+        match self.direction {
+            (0, 1) => '^',
+            (1, 0) => '>',
+            (0, -1) => 'v',
+            (-1, 0) => '<',
+            _ => panic!("unknown direction"),
+        }
+    }
+
     // what *would* the next pos be, if we kept going
     fn next(&self) -> Option<Pos> {
         self.pos.add(self.direction.0, self.direction.1)
@@ -58,6 +76,17 @@ impl Guard {
         }
     }
 
+    // 0 to 3, turning right from up
+    fn direction_index(&self) -> usize {
+        match self.direction {
+            (0, 1) => 0,
+            (1, 0) => 1,
+            (0, -1) => 2,
+            (-1, 0) => 3,
+            _ => panic!("unknown direction"),
+        }
+    }
+
     fn advance(&mut self) {
         self.pos = self.next().unwrap();
     }
@@ -65,38 +94,143 @@ impl Guard {
 
 #[derive(Debug)]
 struct Map {
-    obstacles: HashSet<Pos>,
+    // one bit per cell, see cell()
+    obstacles: BitSet,
     max_x: usize,
     max_y: usize,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Moved,
+    Exited,
+    // obstacles on all four sides, nowhere to go
+    Trapped,
+}
+
+// how a patrol ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    // last position on the map before walking off
+    Exited(Pos),
+    // back in a state it first reached at step entered_at, period steps ago
+    Looped { entered_at: usize, period: usize },
+    Trapped,
+}
+
 impl Map {
-    fn move_guard(&self, guard: &mut Guard, maybe_extra_obstacle: Option<Pos>) -> bool {
+    fn new(obstacles: impl IntoIterator<Item = Pos>, max_x: usize, max_y: usize) -> Map {
+        let mut map = Map {
+            obstacles: BitSet::new((max_x + 1) * (max_y + 1)),
+            max_x,
+            max_y,
+        };
+        for pos in obstacles {
+            map.add_obstacle(pos);
+        }
+        map
+    }
+
+    // dense index of a cell, row by row from the bottom
+    fn cell(&self, pos: Pos) -> usize {
+        pos.y * (self.max_x + 1) + pos.x
+    }
+
+    fn is_obstacle(&self, pos: Pos) -> bool {
+        pos.x <= self.max_x && pos.y <= self.max_y && self.obstacles.contains(self.cell(pos))
+    }
+
+    fn add_obstacle(&mut self, pos: Pos) {
+        let cell = self.cell(pos);
+        self.obstacles.insert(cell);
+    }
+
+    // dense index of a guard state, for a BitSet from guard_states()
+    fn state_index(&self, guard: &Guard) -> usize {
+        self.cell(guard.pos) * 4 + guard.direction_index()
+    }
+
+    fn state_count(&self) -> usize {
+        (self.max_x + 1) * (self.max_y + 1) * 4
+    }
+
+    fn guard_states(&self) -> BitSet {
+        BitSet::new(self.state_count())
+    }
+
+    // hands f this thread's BitSet of guard states, big enough for this map but not cleared
+    fn with_guard_states<T>(&self, f: impl FnOnce(&mut BitSet) -> T) -> T {
+        SCRATCH_STATES.with_borrow_mut(|seen| {
+            seen.grow(self.state_count());
+            f(seen)
+        })
+    }
+
+    fn step_guard(&self, guard: &mut Guard, maybe_extra_obstacle: Option<Pos>) -> Step {
         // if we're not actually using extra_obstacle, put it off the map
         let extra_obstacle = maybe_extra_obstacle.unwrap_or(Pos {
             x: self.max_x + 10,
             y: self.max_y + 10,
         });
 
-        // at most 4 possible directions. maybe the guard is trapped (??)
+        // at most 4 possible directions, after that the guard is boxed in
         for _ in 0..4 {
             if let Some(new_pos) = guard.next() {
                 if new_pos.x > self.max_x || new_pos.y > self.max_y {
-                    return false;
+                    return Step::Exited;
                 }
-                if self.obstacles.contains(&new_pos) || new_pos == extra_obstacle {
+                if self.is_obstacle(new_pos) || new_pos == extra_obstacle {
                     guard.rotate();
                 } else {
                     guard.advance();
-                    return true;
+                    return Step::Moved;
                 }
             } else {
                 // this cat's off the map
-                return false;
+                return Step::Exited;
             }
         }
 
-        false
+        Step::Trapped
+    }
+
+    // false if the guard left the map or can't move
+    fn move_guard(&self, guard: &mut Guard, maybe_extra_obstacle: Option<Pos>) -> bool {
+        self.step_guard(guard, maybe_extra_obstacle) == Step::Moved
+    }
+
+    // walk until the guard leaves, gets stuck, or repeats itself, keeping every cell it stood on
+    // in the order it first got there
+    fn patrol(&self, mut guard: Guard, extra_obstacle: Option<Pos>) -> (Outcome, Vec<Pos>) {
+        let mut cells = BitSet::new((self.max_x + 1) * (self.max_y + 1));
+        cells.insert(self.cell(guard.pos));
+        let mut positions = vec![guard.pos];
+        let mut seen = self.guard_states();
+        seen.insert(self.state_index(&guard));
+        // the step each state in seen was first reached at
+        let mut first_seen: Vec<u32> = vec![0; self.state_count()];
+        let mut step = 0;
+        loop {
+            match self.step_guard(&mut guard, extra_obstacle) {
+                Step::Exited => return (Outcome::Exited(guard.pos), positions),
+                Step::Trapped => return (Outcome::Trapped, positions),
+                Step::Moved => {}
+            }
+            step += 1;
+            let state = self.state_index(&guard);
+            if !seen.insert(state) {
+                let entered_at = first_seen[state] as usize;
+                let outcome = Outcome::Looped {
+                    entered_at,
+                    period: step - entered_at,
+                };
+                return (outcome, positions);
+            }
+            first_seen[state] = step as u32;
+            if cells.insert(self.cell(guard.pos)) {
+                positions.push(guard.pos);
+            }
+        }
     }
 }
 
@@ -109,14 +243,14 @@ impl State {
     fn from_string(s: &str) -> State {
         // flip lines so that +y is "up"
         let lines = s.lines().rev();
-        let mut obstacles: HashSet<Pos> = HashSet::new();
+        let mut obstacles: Vec<Pos> = Vec::new();
         let mut guard: Option<Guard> = None;
         for (y, line) in lines.enumerate() {
             for (x, c) in line.chars().enumerate() {
                 match c {
                     '.' => {}
                     '#' => {
-                        obstacles.insert(Pos { x, y });
+                        obstacles.push(Pos { x, y });
                     }
                     _ => {
                         guard = Some(Guard::from_char(c, Pos { x, y }));
@@ -126,20 +260,154 @@ impl State {
         }
 
         State {
-            map: Map {
+            map: Map::new(
                 obstacles,
-                max_x: s.lines().next().unwrap().len() - 1,
-                max_y: s.lines().count() - 1,
-            },
+                s.lines().next().unwrap().len() - 1,
+                s.lines().count() - 1,
+            ),
             guard: guard.unwrap(),
         }
     }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // cargo run -- patrol [--map=FILE] [--left] [--pass-through] [--no-portals]
+    // any number of guards, and lowercase letter pairs (other than v) are portals, so this one
+    // parses its own map
+    if args.get(1).map(String::as_str) == Some("patrol") {
+        let has = |flag: &str| args.iter().any(|a| a == flag);
+        let rules = patrol::Rules {
+            turn: if has("--left") {
+                patrol::Turn::Left
+            } else {
+                patrol::Turn::Right
+            },
+            guards_block: !has("--pass-through"),
+            portals: !has("--no-portals"),
+        };
+        let input = match args.iter().find_map(|a| a.strip_prefix("--map=")) {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(input) => input,
+                Err(e) => {
+                    eprintln!("can't read {}: {}", path, e);
+                    return;
+                }
+            },
+            None => get_input(false),
+        };
+        let patrol = match patrol::Patrol::from_string(&input, rules) {
+            Ok(patrol) => patrol,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        let (ending, visited) = patrol.run();
+        match ending {
+            patrol::Ending::AllLeft { ticks } => {
+                println!(
+                    "{} guards, all left after {} ticks",
+                    patrol.guards.len(),
+                    ticks
+                )
+            }
+            patrol::Ending::Looped { entered_at, period } => println!(
+                "{} guards, looping every {} ticks from tick {}",
+                patrol.guards.len(),
+                period,
+                entered_at
+            ),
+            patrol::Ending::Trapped { ticks } => println!(
+                "{} guards, stuck after {} ticks",
+                patrol.guards.len(),
+                ticks
+            ),
+        }
+        println!("{} cells visited", visited.len());
+        return;
+    }
+
     let input = get_input(false);
     let state = State::from_string(&input);
 
+    // cargo run -- loops [--render]
+    if args.get(1).map(String::as_str) == Some("loops") {
+        let loops = loops::loop_obstacles(&state.map, state.guard);
+        print!("{}", loops::format_loops(&state.map, &loops));
+        if args.iter().any(|a| a == "--render") {
+            println!();
+            print!("{}", loops::render(&state.map, state.guard, &loops));
+        }
+        return;
+    }
+
+    // cargo run -- replay [--obstacle=ROW,COL] [--delay=MS] [--ascii=FILE] [--ppm=DIR] [--scale=N]
+    // plays in the terminal unless --ascii or --ppm is given
+    if args.get(1).map(String::as_str) == Some("replay") {
+        let flag = |name: &str| {
+            args.iter()
+                .find_map(|a| a.strip_prefix(&format!("--{}=", name)).map(String::from))
+        };
+        let parsed = (|| {
+            let extra = flag("obstacle")
+                .map(|rc| replay::parse_obstacle(&rc, &state.map, state.guard))
+                .transpose()?;
+            let scale = flag("scale").map_or(Ok(4), |n| replay::parse_scale(&n))?;
+            let delay = flag("delay")
+                .map_or(Ok(Duration::from_millis(50)), |ms| replay::parse_delay(&ms))?;
+            Ok::<_, String>((extra, scale, delay))
+        })();
+        let (extra, scale, delay) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        let replay = replay::record(&state.map, state.guard, extra);
+
+        let ascii = flag("ascii");
+        let ppm = flag("ppm");
+        if let Some(path) = &ascii {
+            replay::write_ascii(Path::new(path), &state.map, &replay).unwrap();
+        }
+        if let Some(dir) = &ppm {
+            replay::write_ppm(Path::new(dir), &state.map, &replay, scale).unwrap();
+        }
+        if ascii.is_none() && ppm.is_none() {
+            replay::play(&state.map, &replay, delay);
+        } else {
+            println!(
+                "{} frames{}",
+                replay.states.len(),
+                if replay.looped {
+                    ", ends in a loop"
+                } else {
+                    ""
+                }
+            );
+        }
+        return;
+    }
+
+    // cargo run -- outcome
+    if args.get(1).map(String::as_str) == Some("outcome") {
+        let (outcome, positions) = state.map.patrol(state.guard, None);
+        match outcome {
+            Outcome::Exited(pos) => {
+                print!("exited from row {}, col {}", state.map.max_y - pos.y, pos.x)
+            }
+            Outcome::Looped { entered_at, period } => {
+                print!("looped every {} steps from step {}", period, entered_at)
+            }
+            Outcome::Trapped => print!("trapped"),
+        }
+        println!(" after visiting {} cells", positions.len());
+        return;
+    }
+
     println!("{}", part1(&state.map, state.guard));
     println!("{}", part2(&state.map, state.guard));
 }
@@ -153,48 +421,61 @@ fn part1(map: &Map, guard: Guard) -> usize {
     get_guard_positions(map, guard).len()
 }
 
-fn get_guard_positions(map: &Map, mut guard: Guard) -> HashSet<Pos> {
-    let mut guard_positions: HashSet<Pos> = HashSet::new();
-    guard_positions.insert(guard.pos);
-
-    // seems like cycles are not possible
-    while map.move_guard(&mut guard, None) {
-        guard_positions.insert(guard.pos);
-    }
+// if the patrol loops without any help, this is every cell on the way in and round the loop
+fn get_guard_positions(map: &Map, guard: Guard) -> Vec<Pos> {
+    map.patrol(guard, None).1
+}
 
-    guard_positions
+thread_local! {
+    // one per thread, so each rayon worker wipes and reuses the same one for every candidate
+    // it checks, rather than a new HashSet per candidate
+    static SCRATCH_STATES: RefCell<BitSet> = RefCell::new(BitSet::new(0));
 }
 
 fn part2(map: &Map, guard: Guard) -> usize {
+    let jumps = JumpTable::new(map);
     get_guard_positions(map, guard)
         .par_iter()
-        .filter(|&&pos| pos != guard.pos && check_cycle(map, guard, pos))
+        .filter(|&&pos| {
+            pos != guard.pos && map.with_guard_states(|seen| jumps.check_cycle(guard, pos, seen))
+        })
         .count()
 }
 
-fn check_cycle(map: &Map, mut guard: Guard, extra_obstacle: Pos) -> bool {
-    let mut past_guard_states: HashSet<Guard> = HashSet::new();
-
-    // do-while: doing all the work in the where condition, weird.
-    while {
-        if past_guard_states.contains(&guard) {
-            return true;
-        }
-        past_guard_states.insert(guard);
-        map.move_guard(&mut guard, Some(extra_obstacle))
-    } {}
-
-    false
-}
-
 #[cfg(test)]
 mod tests {
     // cargo test --release -- --nocapture
     // to get better performance and let it print
 
     use super::*;
+    use std::collections::HashSet;
     use std::time::Instant;
 
+    // one cell at a time, the way part2 used to work before the jump table
+    fn part2_stepwise(map: &Map, guard: Guard) -> usize {
+        get_guard_positions(map, guard)
+            .par_iter()
+            .filter(|&&pos| {
+                pos != guard.pos && map.with_guard_states(|seen| check_cycle(map, guard, pos, seen))
+            })
+            .count()
+    }
+
+    // seen gets cleared first, so it can be reused
+    fn check_cycle(map: &Map, mut guard: Guard, extra_obstacle: Pos, seen: &mut BitSet) -> bool {
+        seen.clear();
+
+        // do-while: doing all the work in the where condition, weird.
+        while {
+            if !seen.insert(map.state_index(&guard)) {
+                return true;
+            }
+            map.move_guard(&mut guard, Some(extra_obstacle))
+        } {}
+
+        false
+    }
+
     #[test]
     fn part1_answer() {
         let input = get_input(false);
@@ -214,5 +495,377 @@ mod tests {
         println!("part2 duration (rayon parallelized): {:?}", duration);
 
         assert_eq!(result, 2188);
+
+        let start = Instant::now();
+        let result = part2_stepwise(&state.map, state.guard);
+        let duration = start.elapsed();
+        println!("part2 duration (stepwise): {:?}", duration);
+
+        assert_eq!(result, 2188);
+    }
+
+    #[test]
+    fn toy_answers() {
+        let state = State::from_string(&get_input(true));
+        assert_eq!(part1(&state.map, state.guard), 41);
+        assert_eq!(part2(&state.map, state.guard), 6);
+        assert_eq!(part2_stepwise(&state.map, state.guard), 6);
+    }
+
+    #[test]
+    fn jumping_matches_stepping_everywhere() {
+        let state = State::from_string(&get_input(true));
+        let jumps = JumpTable::new(&state.map);
+        let mut seen = state.map.guard_states();
+        for x in 0..=state.map.max_x {
+            for y in 0..=state.map.max_y {
+                let pos = Pos { x, y };
+                if pos == state.guard.pos {
+                    continue;
+                }
+                assert_eq!(
+                    jumps.check_cycle(state.guard, pos, &mut seen),
+                    check_cycle(&state.map, state.guard, pos, &mut seen),
+                    "{:?}",
+                    pos
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn jumping_when_boxed_in() {
+        // the guard has to turn twice before it can go anywhere
+        let state = State::from_string(".#.\n#^#\n...\n.#.");
+        let jumps = JumpTable::new(&state.map);
+        let mut guard = state.guard;
+        assert!(jumps.jump(&mut guard, None));
+        assert_eq!(guard.pos, Pos { x: 1, y: 1 });
+        assert_eq!(guard.direction, (-1, 0));
+        assert!(!jumps.jump(&mut guard, None));
+    }
+
+    #[test]
+    fn toy_loop_obstacles() {
+        let state = State::from_string(&get_input(true));
+        let loops = loops::loop_obstacles(&state.map, state.guard);
+        let at: Vec<(usize, usize)> = loops
+            .iter()
+            .map(|l| (state.map.max_y - l.obstacle.y, l.obstacle.x))
+            .collect();
+        // (row, col), same six as the puzzle text
+        assert_eq!(at, vec![(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]);
+        for l in &loops {
+            assert!(l.period > 0);
+            assert_eq!(
+                loops::find_loop(&state.map, state.guard, l.obstacle),
+                Some(*l)
+            );
+        }
+
+        let rendered = loops::render(&state.map, state.guard, &loops);
+        assert_eq!(rendered.matches('O').count(), 6);
+        assert_eq!(rendered.lines().nth(6), Some(".#.O^....."));
+    }
+
+    #[test]
+    fn loop_from_the_start() {
+        // up, right, down, left and back where it started
+        let state = State::from_string(".#...\n....#\n.^...\n#....\n...#.");
+        let extra = Pos { x: 0, y: 0 };
+        let info = loops::find_loop(&state.map, state.guard, extra).unwrap();
+        assert_eq!(info.period, 8);
+        assert_eq!(info.entered_at, 0);
+    }
+
+    #[test]
+    fn replay_keeps_the_path_in_order() {
+        let state = State::from_string(&get_input(true));
+        let replay = replay::record(&state.map, state.guard, None);
+        assert!(!replay.looped);
+        assert_eq!(replay.states[0], state.guard);
+        let positions: HashSet<Pos> = replay.states.iter().map(|g| g.pos).collect();
+        let expected: HashSet<Pos> = get_guard_positions(&state.map, state.guard)
+            .into_iter()
+            .collect();
+        assert_eq!(positions, expected);
+        for pair in replay.states.windows(2) {
+            assert_eq!(
+                pair[0].pos.x.abs_diff(pair[1].pos.x) + pair[0].pos.y.abs_diff(pair[1].pos.y),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn replay_stops_at_the_loop() {
+        let state = State::from_string(&get_input(true));
+        let extra = Pos {
+            x: 3,
+            y: state.map.max_y - 6,
+        };
+        let replay = replay::record(&state.map, state.guard, Some(extra));
+        let info = loops::find_loop(&state.map, state.guard, extra).unwrap();
+        assert!(replay.looped);
+        assert_eq!(replay.states.len(), info.entered_at + info.period);
+    }
+
+    #[test]
+    fn replay_obstacle_argument() {
+        let state = State::from_string(&get_input(true));
+        let parse = |rc: &str| replay::parse_obstacle(rc, &state.map, state.guard);
+        assert_eq!(parse("6,3"), Ok(Pos { x: 3, y: 3 }));
+        assert_eq!(parse(" 0 , 0 "), Ok(Pos { x: 0, y: 9 }));
+        assert!(parse("10,3").unwrap_err().contains("off the map"));
+        assert!(parse("3,10").unwrap_err().contains("off the map"));
+        assert!(parse("0,4").unwrap_err().contains("already an obstacle"));
+        assert!(parse("6,4").unwrap_err().contains("where the guard starts"));
+        for bad in ["6", "6,x", "-1,3", ""] {
+            assert!(parse(bad)
+                .unwrap_err()
+                .starts_with("--obstacle wants ROW,COL"));
+        }
+    }
+
+    #[test]
+    fn replay_scale_and_delay_arguments() {
+        assert_eq!(replay::parse_scale("3"), Ok(3));
+        assert_eq!(replay::parse_delay("20"), Ok(Duration::from_millis(20)));
+        assert_eq!(replay::parse_delay("0"), Ok(Duration::ZERO));
+        for bad in ["0", "-2", "x", ""] {
+            assert!(replay::parse_scale(bad).unwrap_err().starts_with("--scale"));
+        }
+        for bad in ["-1", "1.5", "x", ""] {
+            assert!(replay::parse_delay(bad).unwrap_err().starts_with("--delay"));
+        }
+    }
+
+    #[test]
+    fn replay_frames() {
+        let state = State::from_string(&get_input(true));
+        let extra = Pos {
+            x: 3,
+            y: state.map.max_y - 6,
+        };
+        let replay = replay::record(&state.map, state.guard, Some(extra));
+        let mut frames = Vec::new();
+        replay::for_each_frame(&state.map, &replay, |_, grid| {
+            frames.push((replay::ascii(grid), replay::ppm(grid, 2)))
+        });
+        assert_eq!(frames.len(), replay.states.len());
+
+        let (first, image) = &frames[0];
+        assert_eq!(first.lines().nth(6), Some(".#.O^....."));
+        assert_eq!(first.matches('X').count(), 0);
+        let header = b"P6\n20 20\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        assert_eq!(image.len(), header.len() + 20 * 20 * 3);
+
+        let (second, _) = &frames[1];
+        assert_eq!(second.lines().nth(5), Some("....^....."));
+        assert_eq!(second.lines().nth(6), Some(".#.OX....."));
+    }
+
+    #[test]
+    fn patrol_with_puzzle_rules_matches() {
+        let input = get_input(true);
+        let state = State::from_string(&input);
+        let mut patrol = patrol::Patrol::from_string(&input, patrol::Rules::default()).unwrap();
+        let (ending, visited) = patrol.run();
+        assert!(matches!(ending, patrol::Ending::AllLeft { .. }));
+        let expected: HashSet<Pos> = get_guard_positions(&state.map, state.guard)
+            .into_iter()
+            .collect();
+        assert_eq!(visited, expected);
+
+        let extra = Pos {
+            x: 3,
+            y: state.map.max_y - 6,
+        };
+        let info = loops::find_loop(&state.map, state.guard, extra).unwrap();
+        patrol.map.add_obstacle(extra);
+        assert_eq!(
+            patrol.run().0,
+            patrol::Ending::Looped {
+                entered_at: info.entered_at,
+                period: info.period
+            }
+        );
+    }
+
+    #[test]
+    fn patrol_turning_left() {
+        // mirror image of the toy map, so turning left retraces the same path
+        let input = get_input(true);
+        let mirrored: String = input
+            .lines()
+            .map(|line| line.chars().rev().collect::<String>() + "\n")
+            .collect();
+        let left = patrol::Rules {
+            turn: patrol::Turn::Left,
+            ..patrol::Rules::default()
+        };
+        let (ending, visited) = patrol::Patrol::from_string(&mirrored, left).unwrap().run();
+        let (expected_ending, expected) =
+            patrol::Patrol::from_string(&input, patrol::Rules::default())
+                .unwrap()
+                .run();
+        assert_eq!(ending, expected_ending);
+        let max_x = input.lines().next().unwrap().len() - 1;
+        let flipped: HashSet<Pos> = visited
+            .iter()
+            .map(|p| Pos {
+                x: max_x - p.x,
+                y: p.y,
+            })
+            .collect();
+        assert_eq!(flipped, expected);
+    }
+
+    #[test]
+    fn patrol_guards_block_each_other() {
+        // head on in a corridor: they bump, turn, and end up pacing back and forth forever
+        let input = "#####\n#>.<#\n#####";
+        let (ending, _) = patrol::Patrol::from_string(input, patrol::Rules::default())
+            .unwrap()
+            .run();
+        assert!(matches!(ending, patrol::Ending::Looped { .. }));
+
+        // without blocking they walk through each other, but the walls still keep them pacing
+        let ghosts = patrol::Rules {
+            guards_block: false,
+            ..patrol::Rules::default()
+        };
+        let (ending, _) = patrol::Patrol::from_string(input, ghosts).unwrap().run();
+        assert!(matches!(ending, patrol::Ending::Looped { .. }));
+
+        // the back guard is boxed in until the front one walks out, then follows it
+        let input = "####\n#>>.\n####";
+        let (ending, visited) = patrol::Patrol::from_string(input, patrol::Rules::default())
+            .unwrap()
+            .run();
+        assert_eq!(ending, patrol::Ending::AllLeft { ticks: 4 });
+        assert_eq!(visited.len(), 3);
+    }
+
+    #[test]
+    fn patrol_trapped() {
+        let input = ".#.\n#^#\n.#.";
+        let (ending, visited) = patrol::Patrol::from_string(input, patrol::Rules::default())
+            .unwrap()
+            .run();
+        assert_eq!(ending, patrol::Ending::Trapped { ticks: 0 });
+        assert_eq!(visited.len(), 1);
+
+        // the other guard walks off, then the one left behind can't go anywhere
+        let input = "#.##\n#^#>\n####";
+        let (ending, _) = patrol::Patrol::from_string(input, patrol::Rules::default())
+            .unwrap()
+            .run();
+        assert_eq!(ending, patrol::Ending::AllLeft { ticks: 2 });
+        let input = "####\n#^#>\n####";
+        let (ending, _) = patrol::Patrol::from_string(input, patrol::Rules::default())
+            .unwrap()
+            .run();
+        assert_eq!(ending, patrol::Ending::Trapped { ticks: 1 });
+    }
+
+    #[test]
+    fn patrol_guard_facing_down() {
+        // v is a guard, not half of a portal
+        let input = "..#..\n..v..\n.....";
+        for portals in [true, false] {
+            let rules = patrol::Rules {
+                portals,
+                ..patrol::Rules::default()
+            };
+            let patrol = patrol::Patrol::from_string(input, rules).unwrap();
+            assert_eq!(patrol.guards.len(), 1);
+            assert_eq!(patrol.guards[0].direction, (0, -1));
+            assert_eq!(patrol.run().0, patrol::Ending::AllLeft { ticks: 2 });
+        }
+    }
+
+    #[test]
+    fn patrol_bad_maps() {
+        let rules = patrol::Rules::default();
+        let err = patrol::Patrol::from_string("..#\n.^@", rules).unwrap_err();
+        assert!(err.contains("'@' isn't a map symbol"), "{}", err);
+        let err = patrol::Patrol::from_string("a.#\n.^a\n..a", rules).unwrap_err();
+        assert!(
+            err.contains("portal a needs exactly two ends, found 3"),
+            "{}",
+            err
+        );
+        assert!(patrol::Patrol::from_string("", rules).is_err());
+
+        // with portals off, a lone letter is only floor
+        let no_portals = patrol::Rules {
+            portals: false,
+            ..rules
+        };
+        assert!(patrol::Patrol::from_string("b.#\n.^.", no_portals).is_ok());
+    }
+
+    #[test]
+    fn patrol_through_portals() {
+        // straight up into a and straight out of the other a, past the wall
+        let input = "..#..\n..#a.\n..#..\n.a#..\n.^#..";
+        let (ending, visited) = patrol::Patrol::from_string(input, patrol::Rules::default())
+            .unwrap()
+            .run();
+        assert_eq!(ending, patrol::Ending::AllLeft { ticks: 3 });
+        assert!(visited.contains(&Pos { x: 3, y: 4 }));
+        assert!(!visited.contains(&Pos { x: 1, y: 3 }));
+
+        let no_portals = patrol::Rules {
+            portals: false,
+            ..patrol::Rules::default()
+        };
+        let (ending, visited) = patrol::Patrol::from_string(input, no_portals)
+            .unwrap()
+            .run();
+        assert_eq!(ending, patrol::Ending::AllLeft { ticks: 5 });
+        assert!(visited.contains(&Pos { x: 1, y: 3 }));
+    }
+
+    #[test]
+    fn outcomes() {
+        let state = State::from_string(&get_input(true));
+        let (outcome, positions) = state.map.patrol(state.guard, None);
+        assert_eq!(outcome, Outcome::Exited(Pos { x: 7, y: 0 }));
+        assert_eq!(positions.len(), 41);
+
+        let extra = Pos {
+            x: 3,
+            y: state.map.max_y - 6,
+        };
+        let info = loops::find_loop(&state.map, state.guard, extra).unwrap();
+        let (outcome, _) = state.map.patrol(state.guard, Some(extra));
+        assert_eq!(
+            outcome,
+            Outcome::Looped {
+                entered_at: info.entered_at,
+                period: info.period
+            }
+        );
+
+        let state = State::from_string(".#.\n#^#\n.#.");
+        assert_eq!(state.map.patrol(state.guard, None).0, Outcome::Trapped);
+        assert_eq!(part1(&state.map, state.guard), 1);
+    }
+
+    #[test]
+    fn part1_when_the_patrol_already_loops() {
+        // up, right, down, left and back where it started, no extra obstacle needed
+        let state = State::from_string(".#...\n....#\n.^...\n#....\n...#.");
+        assert_eq!(
+            state.map.patrol(state.guard, None).0,
+            Outcome::Looped {
+                entered_at: 0,
+                period: 8
+            }
+        );
+        assert_eq!(part1(&state.map, state.guard), 8);
     }
 }
@@ -0,0 +1,191 @@
+use crate::{Guard, Map, Pos, Step};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+impl Turn {
+    fn apply(self, guard: &mut Guard) {
+        let times = match self {
+            Turn::Right => 1,
+            Turn::Left => 3,
+        };
+        for _ in 0..times {
+            guard.rotate();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rules {
+    pub turn: Turn,
+    // a guard treats another guard like an obstacle. otherwise they walk through each other.
+    pub guards_block: bool,
+    // two cells with the same lowercase letter are a portal. stepping on one puts you on the other,
+    // still facing the same way. off, the letters are just floor. never v, that's always a guard.
+    pub portals: bool,
+}
+
+impl Default for Rules {
+    // the puzzle's rules
+    fn default() -> Rules {
+        Rules {
+            turn: Turn::Right,
+            guards_block: true,
+            portals: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Patrol {
+    pub map: Map,
+    pub guards: Vec<Guard>,
+    portals: HashMap<Pos, Pos>,
+    pub rules: Rules,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ending {
+    // every guard walked off the map
+    AllLeft { ticks: usize },
+    // the guards together are back in a state they've been in before
+    Looped { entered_at: usize, period: usize },
+    // after this many ticks, every guard still on the map is boxed in
+    Trapped { ticks: usize },
+}
+
+impl Patrol {
+    // like State::from_string, but every arrow is a guard (v included), and the other lowercase
+    // letters are portals. Err for anything else on the map, or a portal without exactly two ends.
+    pub fn from_string(s: &str, rules: Rules) -> Result<Patrol, String> {
+        let mut obstacles: Vec<Pos> = Vec::new();
+        let mut guards: Vec<Guard> = Vec::new();
+        let mut letters: HashMap<char, Vec<Pos>> = HashMap::new();
+        // top row first, so guards are numbered in reading order
+        let Some(max_y) = s.lines().count().checked_sub(1) else {
+            return Err("the map is empty".to_string());
+        };
+        for (row, line) in s.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let pos = Pos { x, y: max_y - row };
+                match c {
+                    '.' => {}
+                    '#' => {
+                        obstacles.push(pos);
+                    }
+                    // before the letters, or a guard facing down would be a portal
+                    '^' | '>' | 'v' | '<' => guards.push(Guard::from_char(c, pos)),
+                    'a'..='z' => letters.entry(c).or_default().push(pos),
+                    _ => {
+                        return Err(format!(
+                            "row {}, col {}: {:?} isn't a map symbol",
+                            row, x, c
+                        ))
+                    }
+                }
+            }
+        }
+
+        let mut portals: HashMap<Pos, Pos> = HashMap::new();
+        if rules.portals {
+            for (c, ends) in letters {
+                let [a, b] = ends[..] else {
+                    return Err(format!(
+                        "portal {} needs exactly two ends, found {}",
+                        c,
+                        ends.len()
+                    ));
+                };
+                portals.insert(a, b);
+                portals.insert(b, a);
+            }
+        }
+
+        Ok(Patrol {
+            map: Map::new(obstacles, s.lines().next().unwrap().len() - 1, max_y),
+            guards,
+            portals,
+            rules,
+        })
+    }
+
+    fn blocked(&self, pos: Pos, others: &[Option<Guard>]) -> bool {
+        self.map.is_obstacle(pos)
+            || (self.rules.guards_block && others.iter().flatten().any(|g| g.pos == pos))
+    }
+
+    // same as Map::step_guard, but with the rules
+    fn move_guard(&self, guard: &mut Guard, others: &[Option<Guard>]) -> Step {
+        for _ in 0..4 {
+            let Some(new_pos) = guard.next() else {
+                return Step::Exited;
+            };
+            if new_pos.x > self.map.max_x || new_pos.y > self.map.max_y {
+                return Step::Exited;
+            }
+            // coming out of a portal onto another guard counts as bumping into them
+            let landing = self.portals.get(&new_pos).copied().unwrap_or(new_pos);
+            if self.blocked(new_pos, others) || self.blocked(landing, others) {
+                self.rules.turn.apply(guard);
+            } else {
+                guard.pos = landing;
+                return Step::Moved;
+            }
+        }
+        // boxed in for now, stays put. another guard might move out of the way.
+        Step::Trapped
+    }
+
+    // one tick: each guard still on the map moves once, in order. None is a guard that's left.
+    // false if none of them could move or leave.
+    pub fn tick(&self, guards: &mut [Option<Guard>]) -> bool {
+        let mut changed = false;
+        for i in 0..guards.len() {
+            let Some(mut guard) = guards[i] else {
+                continue;
+            };
+            let (before, rest) = guards.split_at(i);
+            let others: Vec<Option<Guard>> = before.iter().chain(&rest[1..]).copied().collect();
+            guards[i] = match self.move_guard(&mut guard, &others) {
+                Step::Moved => {
+                    changed = true;
+                    Some(guard)
+                }
+                Step::Trapped => Some(guard),
+                Step::Exited => {
+                    changed = true;
+                    None
+                }
+            };
+        }
+        changed
+    }
+
+    // runs until everyone has left or the whole lot of them repeats, and every cell anyone stood on
+    pub fn run(&self) -> (Ending, HashSet<Pos>) {
+        let mut guards: Vec<Option<Guard>> = self.guards.iter().copied().map(Some).collect();
+        let mut visited: HashSet<Pos> = self.guards.iter().map(|g| g.pos).collect();
+        let mut first_seen: HashMap<Vec<Option<Guard>>, usize> = HashMap::new();
+        let mut ticks = 0;
+        while guards.iter().any(Option::is_some) {
+            if let Some(&entered_at) = first_seen.get(&guards) {
+                let ending = Ending::Looped {
+                    entered_at,
+                    period: ticks - entered_at,
+                };
+                return (ending, visited);
+            }
+            first_seen.insert(guards.clone(), ticks);
+            if !self.tick(&mut guards) && guards.iter().any(Option::is_some) {
+                return (Ending::Trapped { ticks }, visited);
+            }
+            visited.extend(guards.iter().flatten().map(|g| g.pos));
+            ticks += 1;
+        }
+        (Ending::AllLeft { ticks }, visited)
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{Guard, Map, Pos};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+// every state the guard goes through, in order
+#[derive(Debug)]
+pub struct Replay {
+    pub states: Vec<Guard>,
+    pub extra_obstacle: Option<Pos>,
+    // stopped because the next state was one we'd already seen, rather than walking off the map
+    pub looped: bool,
+}
+
+pub fn record(map: &Map, mut guard: Guard, extra_obstacle: Option<Pos>) -> Replay {
+    let mut states = vec![guard];
+    let mut seen = map.guard_states();
+    seen.insert(map.state_index(&guard));
+    while map.move_guard(&mut guard, extra_obstacle) {
+        if !seen.insert(map.state_index(&guard)) {
+            return Replay {
+                states,
+                extra_obstacle,
+                looped: true,
+            };
+        }
+        states.push(guard);
+    }
+    Replay {
+        states,
+        extra_obstacle,
+        looped: false,
+    }
+}
+
+// --obstacle=ROW,COL, counting rows from the top like the input. it has to go on empty floor.
+pub fn parse_obstacle(rc: &str, map: &Map, guard: Guard) -> Result<Pos, String> {
+    let usage = || format!("--obstacle wants ROW,COL, got {:?}", rc);
+    let (row, col) = rc.split_once(',').ok_or_else(usage)?;
+    let row: usize = row.trim().parse().map_err(|_| usage())?;
+    let x: usize = col.trim().parse().map_err(|_| usage())?;
+    if row > map.max_y || x > map.max_x {
+        return Err(format!(
+            "--obstacle {} is off the map, rows go to {} and cols to {}",
+            rc, map.max_y, map.max_x
+        ));
+    }
+    let pos = Pos {
+        x,
+        y: map.max_y - row,
+    };
+    if map.is_obstacle(pos) {
+        return Err(format!("--obstacle {} is already an obstacle", rc));
+    }
+    if pos == guard.pos {
+        return Err(format!("--obstacle {} is where the guard starts", rc));
+    }
+    Ok(pos)
+}
+
+// --scale=N, pixels per cell, so it has to be at least 1
+pub fn parse_scale(n: &str) -> Result<usize, String> {
+    match n.trim().parse() {
+        Ok(scale) if scale > 0 => Ok(scale),
+        _ => Err(format!(
+            "--scale wants a whole number of pixels, 1 or more, got {:?}",
+            n
+        )),
+    }
+}
+
+// --delay=MS, between frames
+pub fn parse_delay(ms: &str) -> Result<Duration, String> {
+    ms.trim()
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|_| format!("--delay wants a number of milliseconds, got {:?}", ms))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell {
+    Floor,
+    Obstacle,
+    Extra,
+    Visited,
+    Guard(char),
+}
+
+// hands each frame to f as rows top to bottom, same way round as the input.
+// the grid is updated in place, so long patrols don't need a copy per frame.
+pub fn for_each_frame(map: &Map, replay: &Replay, mut f: impl FnMut(usize, &[Vec<Cell>])) {
+    let mut grid = vec![vec![Cell::Floor; map.max_x + 1]; map.max_y + 1];
+    for (row, cells) in grid.iter_mut().enumerate() {
+        for (x, cell) in cells.iter_mut().enumerate() {
+            if map.is_obstacle(Pos {
+                x,
+                y: map.max_y - row,
+            }) {
+                *cell = Cell::Obstacle;
+            }
+        }
+    }
+    if let Some(pos) = replay.extra_obstacle {
+        grid[map.max_y - pos.y][pos.x] = Cell::Extra;
+    }
+
+    let mut previous: Option<Pos> = None;
+    for (i, guard) in replay.states.iter().enumerate() {
+        if let Some(pos) = previous {
+            grid[map.max_y - pos.y][pos.x] = Cell::Visited;
+        }
+        grid[map.max_y - guard.pos.y][guard.pos.x] = Cell::Guard(guard.to_char());
+        previous = Some(guard.pos);
+        f(i, &grid);
+    }
+}
+
+pub fn ascii(grid: &[Vec<Cell>]) -> String {
+    grid.iter()
+        .map(|row| {
+            let line: String = row
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Floor => '.',
+                    Cell::Obstacle => '#',
+                    Cell::Extra => 'O',
+                    Cell::Visited => 'X',
+                    Cell::Guard(c) => *c,
+                })
+                .collect();
+            line + "\n"
+        })
+        .collect()
+}
+
+// binary ppm, each cell drawn as a scale x scale square
+pub fn ppm(grid: &[Vec<Cell>], scale: usize) -> Vec<u8> {
+    let height = grid.len();
+    let width = grid.first().map_or(0, |row| row.len());
+    let mut out = format!("P6\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+    for row in grid {
+        for _ in 0..scale {
+            for cell in row {
+                let rgb: [u8; 3] = match cell {
+                    Cell::Floor => [32, 32, 32],
+                    Cell::Obstacle => [200, 200, 200],
+                    Cell::Extra => [220, 40, 40],
+                    Cell::Visited => [40, 90, 200],
+                    Cell::Guard(_) => [250, 210, 0],
+                };
+                for _ in 0..scale {
+                    out.extend_from_slice(&rgb);
+                }
+            }
+        }
+    }
+    out
+}
+
+pub fn play(map: &Map, replay: &Replay, delay: Duration) {
+    let total = replay.states.len();
+    for_each_frame(map, replay, |i, grid| {
+        // clear the screen and go back to the top left
+        println!("\x1b[2J\x1b[H{}step {}/{}", ascii(grid), i, total - 1);
+        thread::sleep(delay);
+    });
+    if replay.looped {
+        println!("loop! the next step is a repeat");
+    }
+}
+
+// every frame in one file, each one headed by its step number
+pub fn write_ascii(path: &Path, map: &Map, replay: &Replay) -> io::Result<()> {
+    let mut file = io::BufWriter::new(File::create(path)?);
+    let mut result = Ok(());
+    for_each_frame(map, replay, |i, grid| {
+        if result.is_ok() {
+            result = write!(file, "step {}\n{}\n", i, ascii(grid));
+        }
+    });
+    result?;
+    file.flush()
+}
+
+// one frame_NNNNN.ppm per step
+pub fn write_ppm(dir: &Path, map: &Map, replay: &Replay, scale: usize) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut result = Ok(());
+    for_each_frame(map, replay, |i, grid| {
+        if result.is_ok() {
+            result = fs::write(dir.join(format!("frame_{:05}.ppm", i)), ppm(grid, scale));
+        }
+    });
+    result
+}
@@ -1,26 +1,102 @@
+mod operators;
+mod reverse;
+mod witness;
+
+use operators::OperatorSet;
 use std::fs;
+use std::num::IntErrorKind;
 
 struct Equation {
-    result: u64,
+    // None if a number in the equation doesn't fit in a u64. every operator gives up past
+    // u64::MAX, so that's as good as an overflow on the way: nothing can reach it.
+    result: Option<u64>,
     operands: Vec<u64>,
 }
 
 impl Equation {
     fn from_string(s: &str) -> Equation {
         let parts: Vec<&str> = s.split(": ").collect();
+        let operands: Option<Vec<u64>> = parts[1].split(" ").map(parse_number).collect();
         Equation {
-            result: parts[0].parse().unwrap(),
-            operands: parts[1].split(" ").map(|n| n.parse().unwrap()).collect(),
+            result: parse_number(parts[0]).filter(|_| operands.is_some()),
+            operands: operands.unwrap_or_default(),
         }
     }
 }
 
+// None if it's too big for a u64, anything else that isn't a number is still a bad input
+fn parse_number(s: &str) -> Option<u64> {
+    match s.parse() {
+        Ok(n) => Some(n),
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => None,
+        Err(e) => panic!("{:?}: {}", s, e),
+    }
+}
+
 fn main() {
     let input = get_input(false);
     let equations: Vec<Equation> = input.lines().map(Equation::from_string).collect();
+    let args: Vec<String> = std::env::args().collect();
+
+    // cargo run -- explain [--all] [--ops=...]
+    // how each equation can be solved, part 2's operators unless --ops says otherwise
+    if args.get(1).map(String::as_str) == Some("explain") {
+        let operators = match args
+            .iter()
+            .find_map(|a| a.strip_prefix("--ops="))
+            .map_or(Ok(operators::PART2.to_vec()), operators::parse_set)
+        {
+            Ok(operators) => operators,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        let show_all = args.iter().any(|a| a == "--all");
+        for (line, equation) in input.lines().zip(&equations) {
+            let count = witness::count(equation, &operators);
+            if count == 0 {
+                // the result as written, it might not fit in a u64
+                println!("{}: no solution", line.split(": ").next().unwrap());
+            } else if show_all {
+                for w in witness::all(equation, &operators) {
+                    println!("{}", witness::format(equation, &w));
+                }
+                println!("  {} solution(s)", count);
+            } else {
+                let w = witness::first(equation, &operators).unwrap();
+                println!("{} ({} solution(s))", witness::format(equation, &w), count);
+            }
+        }
+        return;
+    }
+
+    // cargo run -- --ops=+,*,||,-,^,**
+    if let Some(ops) = args.iter().find_map(|a| a.strip_prefix("--ops=")) {
+        let operators = match operators::parse_set(ops) {
+            Ok(operators) => operators,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        println!("{}", solution(&equations, &operators));
+        if operators::invertible(&operators) {
+            println!("{} (reverse)", reverse::solution(&equations, &operators));
+        }
+        return;
+    }
 
-    println!("part 1: {}", solution(&equations, false));
-    println!("part 2: {}", solution(&equations, true));
+    println!("part 1: {}", solution(&equations, operators::PART1));
+    println!("part 2: {}", solution(&equations, operators::PART2));
+    println!(
+        "part 1 (reverse): {}",
+        reverse::solution(&equations, operators::PART1)
+    );
+    println!(
+        "part 2 (reverse): {}",
+        reverse::solution(&equations, operators::PART2)
+    );
 }
 
 fn get_input(toy: bool) -> String {
@@ -28,25 +104,33 @@ fn get_input(toy: bool) -> String {
     fs::read_to_string(path).unwrap()
 }
 
-fn solution(equations: &[Equation], is_part_2: bool) -> u64 {
+// u128 so a handful of results near u64::MAX can't overflow the total
+fn solution(equations: &[Equation], operators: &OperatorSet) -> u128 {
     equations
         .iter()
-        .filter(|e| is_valid(e, is_part_2))
-        .map(|e| e.result)
+        .filter(|e| is_valid(e, operators))
+        .map(|e| e.result.unwrap() as u128)
         .sum()
 }
 
-fn is_valid(equation: &Equation, is_part_2: bool) -> bool {
-    can_hit_result(
-        equation.result,
-        equation.operands[0],
-        &equation.operands[1..],
-        is_part_2,
-    )
+fn is_valid(equation: &Equation, operators: &OperatorSet) -> bool {
+    equation.result.is_some_and(|result| {
+        can_hit_result(
+            result,
+            equation.operands[0],
+            &equation.operands[1..],
+            operators,
+        )
+    })
 }
 
-fn can_hit_result(desired_result: u64, operand1: u64, remaining: &[u64], is_part_2: bool) -> bool {
-    if operand1 > desired_result {
+fn can_hit_result(
+    desired_result: u64,
+    operand1: u64,
+    remaining: &[u64],
+    operators: &OperatorSet,
+) -> bool {
+    if operators::overshot(operand1, desired_result, remaining, operators) {
         return false;
     }
 
@@ -55,34 +139,26 @@ fn can_hit_result(desired_result: u64, operand1: u64, remaining: &[u64], is_part
     }
 
     let operand2 = remaining[0];
-    can_hit_result(
-        desired_result,
-        operand1 + operand2,
-        &remaining[1..],
-        is_part_2,
-    ) || can_hit_result(
-        desired_result,
-        operand1 * operand2,
-        &remaining[1..],
-        is_part_2,
-    ) || (is_part_2
-        && can_hit_result(
-            desired_result,
-            format!("{}{}", operand1, operand2).parse().unwrap(),
-            &remaining[1..],
-            is_part_2,
-        ))
+    operators.iter().any(|op| {
+        op.apply(operand1, operand2)
+            .is_some_and(|next| can_hit_result(desired_result, next, &remaining[1..], operators))
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    // cargo test --release -- --nocapture
+    // to get better performance and let it print
+
     use super::*;
+    use operators::Operator;
+    use std::time::Instant;
 
     #[test]
     fn part1_answer() {
         let input = get_input(false);
         let equations: Vec<Equation> = input.lines().map(Equation::from_string).collect();
-        let result = solution(&equations, false);
+        let result = solution(&equations, operators::PART1);
         assert_eq!(result, 1545311493300);
     }
 
@@ -90,7 +166,311 @@ mod tests {
     fn part2_answer() {
         let input = get_input(false);
         let equations: Vec<Equation> = input.lines().map(Equation::from_string).collect();
-        let result = solution(&equations, true);
+        let result = solution(&equations, operators::PART2);
         assert_eq!(result, 169122112716571);
     }
+
+    #[test]
+    fn toy_answers() {
+        let input = get_input(true);
+        let equations: Vec<Equation> = input.lines().map(Equation::from_string).collect();
+        assert_eq!(solution(&equations, operators::PART1), 3749);
+        assert_eq!(solution(&equations, operators::PART2), 11387);
+        assert_eq!(reverse::solution(&equations, operators::PART1), 3749);
+        assert_eq!(reverse::solution(&equations, operators::PART2), 11387);
+    }
+
+    #[test]
+    fn reverse_matches_forward() {
+        let input = get_input(true);
+        // every toy equation and a few with zeros, plus each one with the result nudged so most of
+        // them fail
+        let zeros = [
+            "44: 15 2 0 26 18",
+            "0: 5 0",
+            "0: 0 5",
+            "3: 0 0 3",
+            "10: 1 0 10",
+        ];
+        for line in input.lines().chain(zeros) {
+            let mut equation = Equation::from_string(line);
+            for _ in 0..3 {
+                for operators in [operators::PART1, operators::PART2] {
+                    assert_eq!(
+                        reverse::is_valid(&equation, operators),
+                        is_valid(&equation, operators),
+                        "{}",
+                        line
+                    );
+                }
+                equation.result = equation.result.map(|r| r + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_operands() {
+        // times 0 throws away everything before it, and a total past the result can come back
+        for line in [
+            "44: 15 2 0 26 18",
+            "0: 5 0",
+            "0: 0 0",
+            "7: 0 7",
+            "0: 9 9 0",
+            "12: 12 0 0 12",
+        ] {
+            let equation = Equation::from_string(line);
+            for operators in [operators::PART1, operators::PART2] {
+                assert!(is_valid(&equation, operators), "{}", line);
+                assert!(reverse::is_valid(&equation, operators), "{}", line);
+                assert!(witness::first(&equation, operators).is_some(), "{}", line);
+            }
+        }
+        // times 0 gives 0, not anything it likes
+        let equation = Equation::from_string("1: 5 0");
+        assert!(!is_valid(&equation, operators::PART1));
+        assert!(!reverse::is_valid(&equation, operators::PART1));
+        // and whatever comes before it still has to fit in a u64
+        let equation = Equation::from_string("0: 18446744073709551615 2 0");
+        assert!(!is_valid(&equation, operators::PART1));
+        assert!(!reverse::is_valid(&equation, operators::PART1));
+        let equation = Equation::from_string("0: 18446744073709551615 0 0");
+        assert!(is_valid(&equation, operators::PART1));
+        assert!(reverse::is_valid(&equation, operators::PART1));
+    }
+
+    #[test]
+    fn reverse_on_long_operand_lists() {
+        // 13 operands is 3^12 combinations going forwards
+        // 3 * 7 + 2 || 9 + 4 * 1 || 8 + 5 + 6 + 2 + 3 + 7 + 9 = 2470
+        let reachable = Equation::from_string("2470: 3 7 2 9 4 1 8 5 6 2 3 7 9");
+        let unreachable = Equation {
+            result: Some(999_999_999_999),
+            operands: reachable.operands.clone(),
+        };
+
+        for equation in [&reachable, &unreachable] {
+            let start = Instant::now();
+            let forward = is_valid(equation, operators::PART2);
+            println!("forward: {:?}", start.elapsed());
+            let start = Instant::now();
+            let backward = reverse::is_valid(equation, operators::PART2);
+            println!("reverse: {:?}", start.elapsed());
+            assert_eq!(forward, backward);
+        }
+        assert!(reverse::is_valid(&reachable, operators::PART2));
+        assert!(!reverse::is_valid(&unreachable, operators::PART2));
+    }
+
+    #[test]
+    fn other_operator_sets() {
+        let equation = Equation::from_string("4: 10 6");
+        let minus = operators::parse_set("+,-").unwrap();
+        assert!(is_valid(&equation, &minus));
+        assert!(reverse::is_valid(&equation, &minus));
+        // 10 is already past 4, but minus can bring it back, so no pruning
+        assert!(!is_valid(&equation, operators::PART2));
+
+        // 5 ^ 3 = 6, 6 ** 2 = 36
+        let equation = Equation::from_string("36: 5 3 2");
+        assert!(is_valid(&equation, &operators::parse_set("^,**").unwrap()));
+        assert!(!is_valid(&equation, &operators::parse_set("^,+").unwrap()));
+        assert!(!reverse::is_valid(
+            &equation,
+            &operators::parse_set("^,*").unwrap()
+        ));
+        assert!(!operators::invertible(
+            &operators::parse_set("+,**").unwrap()
+        ));
+
+        assert_eq!(
+            operators::parse_set("+,%").err(),
+            Some("--ops wants some of +,*,||,-,^,**, got \"%\"".to_string())
+        );
+        assert!(operators::parse_set("").is_err());
+    }
+
+    #[test]
+    fn inverses_undo_apply() {
+        for op in operators::ALL {
+            let Some(inverse) = op.inverse() else {
+                continue;
+            };
+            for a in 1..40 {
+                for b in 1..40 {
+                    if let Some(result) = op.apply(a, b) {
+                        assert_eq!(inverse(result, b), Some(a), "{} {} {}", a, op.symbol(), b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn toy_witnesses() {
+        let input = get_input(true);
+        let equations: Vec<Equation> = input.lines().map(Equation::from_string).collect();
+        let last = equations.last().unwrap();
+        let w = witness::first(last, operators::PART1).unwrap();
+        assert_eq!(witness::format(last, &w), "292 = 11 + 6 * 16 + 20");
+
+        // 3267 is the one with two ways in the puzzle text
+        let formatted: Vec<String> = witness::all(&equations[1], operators::PART1)
+            .iter()
+            .map(|w| witness::format(&equations[1], w))
+            .collect();
+        assert_eq!(
+            formatted,
+            vec!["3267 = 81 + 40 * 27", "3267 = 81 * 40 + 27"]
+        );
+
+        let w = witness::first(&equations[4], operators::PART2).unwrap();
+        assert_eq!(witness::format(&equations[4], &w), "7290 = 6 * 8 || 6 * 15");
+
+        // a witness exactly when the equation is valid, and it really does add up
+        for operators in [operators::PART1, operators::PART2] {
+            for equation in &equations {
+                let witnesses = witness::all(equation, operators);
+                assert_eq!(witnesses.len(), witness::count(equation, operators));
+                assert_eq!(!witnesses.is_empty(), is_valid(equation, operators));
+                for w in witnesses {
+                    let total = w
+                        .iter()
+                        .zip(&equation.operands[1..])
+                        .fold(equation.operands[0], |acc, (op, &b)| {
+                            op.apply(acc, b).unwrap()
+                        });
+                    assert_eq!(Some(total), equation.result);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overflow_is_a_dead_end() {
+        // concatenating lands exactly on u64::MAX
+        let equation = Equation::from_string("18446744073709551615: 18446744073709551 615");
+        assert!(is_valid(&equation, operators::PART2));
+        assert!(reverse::is_valid(&equation, operators::PART2));
+        assert_eq!(witness::count(&equation, operators::PART2), 1);
+
+        // every operator overflows here
+        let equation = Equation::from_string("5: 99999999999 99999999999");
+        assert!(!is_valid(&equation, operators::PART2));
+        assert!(!reverse::is_valid(&equation, operators::PART2));
+        let equation = Equation::from_string("5: 18446744073709551615 18446744073709551615");
+        assert!(!is_valid(
+            &equation,
+            &operators::parse_set("+,*,||,**").unwrap()
+        ));
+        assert!(!reverse::is_valid(
+            &equation,
+            &operators::parse_set("+,*,||,-").unwrap()
+        ));
+
+        // two of those can still be added up
+        let big = vec![
+            Equation::from_string("18446744073709551615: 18446744073709551 615"),
+            Equation::from_string("18446744073709551615: 18446744073709551614 1"),
+        ];
+        assert_eq!(solution(&big, operators::PART2), 2 * u64::MAX as u128);
+        assert_eq!(
+            reverse::solution(&big, operators::PART2),
+            2 * u64::MAX as u128
+        );
+    }
+
+    #[test]
+    fn numbers_past_u64_are_unreachable() {
+        // one past u64::MAX, and 25 digits
+        let lines = [
+            "18446744073709551616: 18446744073709551 616",
+            "1000000000000000000000000: 1000000000000 1000000000000",
+            "10: 5 100000000000000000000",
+        ];
+        let equations: Vec<Equation> = lines.iter().map(|l| Equation::from_string(l)).collect();
+        for equation in &equations {
+            assert_eq!(equation.result, None);
+            assert!(!is_valid(equation, operators::PART2));
+            assert!(!reverse::is_valid(equation, operators::PART2));
+            assert_eq!(witness::count(equation, operators::PART2), 0);
+        }
+        assert_eq!(solution(&equations, operators::PART2), 0);
+        assert_eq!(reverse::solution(&equations, operators::PART2), 0);
+    }
+
+    // xorshift64, so the random tests don't need the rand crate
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    // random operands of every length, not just ones near u64::MAX
+    fn random_operand(state: &mut u64) -> u64 {
+        let digits = xorshift(state) % 21;
+        match digits {
+            0 => 0,
+            20 => xorshift(state),
+            _ => xorshift(state) % 10u64.pow(digits as u32),
+        }
+    }
+
+    #[test]
+    fn arithmetic_concat_matches_string_concat() {
+        let concat = operators::Concat;
+        let unconcat = concat.inverse().unwrap();
+        let mut edges: Vec<u64> = vec![0, 1, u64::MAX, u64::MAX / 10];
+        for p in 0..20 {
+            let power = 10u64.pow(p);
+            edges.extend([power - 1, power, power + 1]);
+        }
+        let mut pairs: Vec<(u64, u64)> = edges
+            .iter()
+            .flat_map(|&a| edges.iter().map(move |&b| (a, b)))
+            .collect();
+        let mut state = 0x2024_0007;
+        pairs
+            .extend((0..200_000).map(|_| (random_operand(&mut state), random_operand(&mut state))));
+
+        for (a, b) in pairs {
+            let result = concat.apply(a, b);
+            assert_eq!(result, operators::concat_by_string(a, b), "{} || {}", a, b);
+            // and it undoes, as long as a didn't disappear as a leading zero
+            if let Some(result) = result.filter(|_| a > 0) {
+                assert_eq!(unconcat(result, b), Some(a), "{} || {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn concat_timing() {
+        let mut state = 0x2024_0007;
+        let pairs: Vec<(u64, u64)> = (0..1_000_000)
+            .map(|_| {
+                (
+                    xorshift(&mut state) % 1_000_000,
+                    xorshift(&mut state) % 1_000,
+                )
+            })
+            .collect();
+
+        let start = Instant::now();
+        let by_string: u64 = pairs
+            .iter()
+            .map(|&(a, b)| operators::concat_by_string(a, b).unwrap())
+            .fold(0, u64::wrapping_add);
+        println!("concat duration (string): {:?}", start.elapsed());
+
+        let concat = operators::Concat;
+        let start = Instant::now();
+        let by_arithmetic: u64 = pairs
+            .iter()
+            .map(|&(a, b)| concat.apply(a, b).unwrap())
+            .fold(0, u64::wrapping_add);
+        println!("concat duration (arithmetic): {:?}", start.elapsed());
+
+        assert_eq!(by_string, by_arithmetic);
+    }
 }
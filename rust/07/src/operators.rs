@@ -0,0 +1,190 @@
+// anything that can go between two operands. the search only ever sees this trait.
+pub trait Operator: Sync {
+    fn symbol(&self) -> &'static str;
+
+    // None if the answer doesn't fit in a u64 (overflowing, or subtracting past zero). the
+    // searches treat that as a dead end rather than panicking.
+    fn apply(&self, a: u64, b: u64) -> Option<u64>;
+
+    // how to undo it, for the reverse solver. None if it can't be undone.
+    fn inverse(&self) -> Option<Inverse>;
+
+    // apply(a, b) >= a for any positive operands, so once the running total is past the
+    // result it can't come back down
+    fn never_shrinks(&self) -> bool {
+        false
+    }
+
+    // Some(r) if apply(a, b) is r whatever a is, like multiplying by 0. inverse can't get a back
+    // out of that, so the reverse solver checks this as well.
+    fn absorbs(&self, _b: u64) -> Option<u64> {
+        None
+    }
+}
+
+// given result and b, the a that makes apply(a, b) == result, if there is one
+pub type Inverse = fn(u64, u64) -> Option<u64>;
+
+pub struct Add;
+pub struct Mul;
+pub struct Concat;
+pub struct Sub;
+pub struct Xor;
+pub struct Pow;
+
+impl Operator for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_add(b)
+    }
+    fn inverse(&self) -> Option<Inverse> {
+        Some(|result, b| result.checked_sub(b))
+    }
+    fn never_shrinks(&self) -> bool {
+        true
+    }
+}
+
+impl Operator for Mul {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_mul(b)
+    }
+    fn inverse(&self) -> Option<Inverse> {
+        Some(|result, b| (b != 0 && result.is_multiple_of(b)).then(|| result / b))
+    }
+    fn never_shrinks(&self) -> bool {
+        true
+    }
+    fn absorbs(&self, b: u64) -> Option<u64> {
+        (b == 0).then_some(0)
+    }
+}
+
+// 10, 100, ..., 10^19. 10^20 doesn't fit.
+const POWERS_OF_TEN: [u64; 19] = {
+    let mut powers = [10; 19];
+    let mut i = 1;
+    while i < 19 {
+        powers[i] = powers[i - 1] * 10;
+        i += 1;
+    }
+    powers
+};
+
+// what a has to be multiplied by to make room for b's digits. None for a 20 digit b.
+fn shift(b: u64) -> Option<u64> {
+    POWERS_OF_TEN.iter().copied().find(|&p| p > b)
+}
+
+// 12 || 345 = 12 * 1000 + 345 = 12345
+impl Operator for Concat {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        match shift(b) {
+            Some(shift) => a.checked_mul(shift)?.checked_add(b),
+            // a 20 digit b can't have anything in front of it, except a leading zero
+            None => (a == 0).then_some(b),
+        }
+    }
+    // strip b's digits off the end of the result, if that's what it ends in
+    fn inverse(&self) -> Option<Inverse> {
+        Some(|result, b| {
+            let shift = shift(b)?;
+            (result % shift == b).then(|| result / shift)
+        })
+    }
+    fn never_shrinks(&self) -> bool {
+        true
+    }
+}
+
+impl Operator for Sub {
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_sub(b)
+    }
+    fn inverse(&self) -> Option<Inverse> {
+        Some(|result, b| result.checked_add(b))
+    }
+}
+
+impl Operator for Xor {
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        Some(a ^ b)
+    }
+    fn inverse(&self) -> Option<Inverse> {
+        Some(|result, b| Some(result ^ b))
+    }
+}
+
+// no inverse, taking roots isn't worth it. sets with this in can only use the forward search.
+impl Operator for Pow {
+    fn symbol(&self) -> &'static str {
+        "**"
+    }
+    fn apply(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_pow(u32::try_from(b).ok()?)
+    }
+    fn inverse(&self) -> Option<Inverse> {
+        None
+    }
+    fn never_shrinks(&self) -> bool {
+        true
+    }
+}
+
+// the way Concat used to do it, kept to check and time the arithmetic against
+#[cfg(test)]
+pub fn concat_by_string(a: u64, b: u64) -> Option<u64> {
+    format!("{}{}", a, b).parse().ok()
+}
+
+pub type OperatorSet = [&'static dyn Operator];
+
+pub const PART1: &OperatorSet = &[&Add, &Mul];
+pub const PART2: &OperatorSet = &[&Add, &Mul, &Concat];
+pub const ALL: &OperatorSet = &[&Add, &Mul, &Concat, &Sub, &Xor, &Pow];
+
+// "+,*,||" -> those three
+pub fn parse_set(s: &str) -> Result<Vec<&'static dyn Operator>, String> {
+    s.split(',')
+        .map(|symbol| {
+            ALL.iter()
+                .find(|op| op.symbol() == symbol)
+                .copied()
+                .ok_or_else(|| {
+                    let known: Vec<&str> = ALL.iter().map(|op| op.symbol()).collect();
+                    format!("--ops wants some of {}, got {:?}", known.join(","), symbol)
+                })
+        })
+        .collect()
+}
+
+pub fn invertible(operators: &OperatorSet) -> bool {
+    operators.iter().all(|op| op.inverse().is_some())
+}
+
+// the forward searches can give up once the total is past the result, but only if nothing left
+// can bring it back down. a 0 still to come can (times 0 is 0), so they keep going then.
+pub fn overshot(
+    total: u64,
+    desired_result: u64,
+    remaining: &[u64],
+    operators: &OperatorSet,
+) -> bool {
+    total > desired_result
+        && !remaining.contains(&0)
+        && operators.iter().all(|op| op.never_shrinks())
+}
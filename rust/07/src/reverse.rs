@@ -0,0 +1,52 @@
+use crate::operators::{self, OperatorSet};
+use crate::Equation;
+
+// works from the result back to the first operand, undoing the last operator each time.
+// each operator's inverse says whether the last operand can be undone: subtracting needs room,
+// dividing needs no remainder, and un-concatenating needs the result to end in its digits.
+// most branches die straight away. every operator needs an inverse for this to work.
+pub fn is_valid(equation: &Equation, operators: &OperatorSet) -> bool {
+    assert!(
+        operators::invertible(operators),
+        "the reverse solver needs every operator to have an inverse"
+    );
+    equation
+        .result
+        .is_some_and(|result| can_undo(result, &equation.operands, operators))
+}
+
+pub fn solution(equations: &[Equation], operators: &OperatorSet) -> u128 {
+    equations
+        .iter()
+        .filter(|e| is_valid(e, operators))
+        .map(|e| e.result.unwrap() as u128)
+        .sum()
+}
+
+fn can_undo(result: u64, operands: &[u64], operators: &OperatorSet) -> bool {
+    let (&last, rest) = operands.split_last().unwrap();
+    if rest.is_empty() {
+        return result == last;
+    }
+
+    operators.iter().any(|op| {
+        // times 0 wipes out whatever came before, so that only has to come to something
+        if op.absorbs(last) == Some(result) && comes_to_something(rest[0], &rest[1..], operators) {
+            return true;
+        }
+        op.inverse()
+            .and_then(|inverse| inverse(result, last))
+            .is_some_and(|before| can_undo(before, rest, operators))
+    })
+}
+
+// whether some choice of operators gets through the rest without overflowing
+fn comes_to_something(total: u64, remaining: &[u64], operators: &OperatorSet) -> bool {
+    let Some((&next, rest)) = remaining.split_first() else {
+        return true;
+    };
+    operators.iter().any(|op| {
+        op.apply(total, next)
+            .is_some_and(|total| comes_to_something(total, rest, operators))
+    })
+}
@@ -0,0 +1,100 @@
+use crate::operators::{self, Operator, OperatorSet};
+use crate::Equation;
+
+// the operators that go between the operands, left to right
+pub type Witness = Vec<&'static dyn Operator>;
+
+// same search as can_hit_result, but it keeps track of the operators on the way down and hands
+// each full solution to found. found returns true to stop looking.
+fn search(
+    desired_result: u64,
+    operand1: u64,
+    remaining: &[u64],
+    operators: &OperatorSet,
+    path: &mut Witness,
+    found: &mut impl FnMut(&Witness) -> bool,
+) -> bool {
+    if operators::overshot(operand1, desired_result, remaining, operators) {
+        return false;
+    }
+
+    if remaining.is_empty() {
+        return operand1 == desired_result && found(path);
+    }
+
+    for &op in operators {
+        if let Some(next) = op.apply(operand1, remaining[0]) {
+            path.push(op);
+            let stop = search(
+                desired_result,
+                next,
+                &remaining[1..],
+                operators,
+                path,
+                found,
+            );
+            path.pop();
+            if stop {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn search_equation(
+    equation: &Equation,
+    operators: &OperatorSet,
+    found: &mut impl FnMut(&Witness) -> bool,
+) {
+    let Some(result) = equation.result else {
+        return;
+    };
+    let mut path = Vec::with_capacity(equation.operands.len() - 1);
+    search(
+        result,
+        equation.operands[0],
+        &equation.operands[1..],
+        operators,
+        &mut path,
+        found,
+    );
+}
+
+pub fn first(equation: &Equation, operators: &OperatorSet) -> Option<Witness> {
+    let mut witness = None;
+    search_equation(equation, operators, &mut |path| {
+        witness = Some(path.clone());
+        true
+    });
+    witness
+}
+
+// in the order the operators are listed in the set, first operator changing slowest
+pub fn all(equation: &Equation, operators: &OperatorSet) -> Vec<Witness> {
+    let mut witnesses = Vec::new();
+    search_equation(equation, operators, &mut |path| {
+        witnesses.push(path.clone());
+        false
+    });
+    witnesses
+}
+
+// same as all().len(), without keeping them
+pub fn count(equation: &Equation, operators: &OperatorSet) -> usize {
+    let mut count = 0;
+    search_equation(equation, operators, &mut |_| {
+        count += 1;
+        false
+    });
+    count
+}
+
+// 292 = 11 + 6 * 16 + 20. there's only a witness if the result fits.
+pub fn format(equation: &Equation, witness: &Witness) -> String {
+    let mut out = format!("{} = {}", equation.result.unwrap(), equation.operands[0]);
+    for (op, operand) in witness.iter().zip(&equation.operands[1..]) {
+        out += &format!(" {} {}", op.symbol(), operand);
+    }
+    out
+}